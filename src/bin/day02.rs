@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 use aoc_helpers::{
     anyhow,
     scaffold::{solve, Problem, RowsOfChars},
@@ -57,6 +59,107 @@ impl Keypad {
         }
         self.keypad[self.row][self.col].unwrap()
     }
+
+    fn find_key(&self, key: char) -> Option<(usize, usize)> {
+        self.keypad.iter().enumerate().find_map(|(row, keys)| {
+            keys.iter()
+                .position(|k| *k == Some(key))
+                .map(|col| (row, col))
+        })
+    }
+
+    fn neighbours(
+        &self,
+        (row, col): (usize, usize),
+    ) -> impl Iterator<Item = (Direction, (usize, usize))> + '_ {
+        [
+            (Direction::Up, row.checked_sub(1).map(|r| (r, col))),
+            (Direction::Down, Some((row + 1, col))),
+            (Direction::Left, col.checked_sub(1).map(|c| (row, c))),
+            (Direction::Right, Some((row, col + 1))),
+        ]
+        .into_iter()
+        .filter_map(|(direction, pos)| {
+            pos.filter(|&(r, c)| {
+                self.keypad
+                    .get(r)
+                    .and_then(|keys| keys.get(c))
+                    .map(Option::is_some)
+                    .unwrap_or_default()
+            })
+            .map(|pos| (direction, pos))
+        })
+    }
+
+    fn shortest_paths(&self, from: (usize, usize), to: (usize, usize)) -> Vec<Vec<Direction>> {
+        // distances to the target (moves are symmetric so they work both ways)
+        let mut distances = vec![vec![None; self.keypad[0].len()]; self.keypad.len()];
+        distances[to.0][to.1] = Some(0);
+        let mut queue = VecDeque::from([to]);
+        while let Some(pos) = queue.pop_front() {
+            let dist = distances[pos.0][pos.1].expect("queued positions have distance");
+            for (_, (r, c)) in self.neighbours(pos) {
+                if distances[r][c].is_none() {
+                    distances[r][c] = Some(dist + 1);
+                    queue.push_back((r, c));
+                }
+            }
+        }
+
+        // walk down the distances from the start collecting all the paths
+        let mut paths = Vec::new();
+        let mut stack = vec![(from, Vec::new())];
+        while let Some((pos, path)) = stack.pop() {
+            let dist = match distances[pos.0][pos.1] {
+                Some(0) => {
+                    paths.push(path);
+                    continue;
+                }
+                Some(dist) => dist,
+                None => continue,
+            };
+            for (direction, (r, c)) in self.neighbours(pos) {
+                if distances[r][c] == Some(dist - 1) {
+                    let mut path = path.clone();
+                    path.push(direction);
+                    stack.push(((r, c), path));
+                }
+            }
+        }
+        paths.reverse();
+        paths
+    }
+
+    // every line has at least one alternative
+    fn all_instructions_for(&self, code: &str) -> anyhow::Result<Vec<Vec<Vec<Direction>>>> {
+        let mut position = (self.row, self.col);
+        let mut result = Vec::new();
+        for key in code.chars() {
+            let target = self
+                .find_key(key)
+                .ok_or_else(|| anyhow::anyhow!("Key {} isn't on this keypad", key))?;
+            let paths = self.shortest_paths(position, target);
+            if paths.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Key {} can't be reached from row {} column {}",
+                    key,
+                    position.0,
+                    position.1
+                ));
+            }
+            result.push(paths);
+            position = target;
+        }
+        Ok(result)
+    }
+
+    fn instructions_for(&self, code: &str) -> anyhow::Result<Vec<Vec<Direction>>> {
+        Ok(self
+            .all_instructions_for(code)?
+            .into_iter()
+            .filter_map(|alternatives| alternatives.into_iter().next())
+            .collect())
+    }
 }
 
 impl Default for Keypad {
@@ -93,6 +196,21 @@ impl TryFrom<char> for Direction {
     }
 }
 
+impl From<Direction> for char {
+    fn from(direction: Direction) -> Self {
+        match direction {
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        }
+    }
+}
+
+fn format_directions(directions: &[Direction]) -> String {
+    directions.iter().map(|d| char::from(*d)).collect()
+}
+
 impl Problem for Day02 {
     type Input = RowsOfChars<Direction>;
     type Part1 = String;
@@ -117,8 +235,40 @@ impl Problem for Day02 {
     }
 }
 
+fn print_instructions_for(keypad: Keypad, code: &str, all: bool) -> anyhow::Result<()> {
+    if all {
+        for alternatives in keypad.all_instructions_for(code)? {
+            let alternatives = alternatives
+                .iter()
+                .map(|a| format_directions(a))
+                .collect::<Vec<_>>();
+            println!("{}", alternatives.join(" "));
+        }
+    } else {
+        for line in keypad.instructions_for(code)? {
+            println!("{}", format_directions(&line));
+        }
+    }
+    Ok(())
+}
+
 fn main() {
-    solve::<Day02>(include_str!("../../inputs/day02.txt"));
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let typed = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["type", code] => print_instructions_for(Keypad::default(), code, false),
+        ["type", code, "--all"] => print_instructions_for(Keypad::default(), code, true),
+        ["type2", code] => print_instructions_for(Keypad::new(KEYPAD2, 2, 0), code, false),
+        ["type2", code, "--all"] => print_instructions_for(Keypad::new(KEYPAD2, 2, 0), code, true),
+        _ => return solve::<Day02>(include_str!("../../inputs/day02.txt")),
+    };
+    if let Err(e) = typed {
+        eprintln!("Code can't be typed on this keypad: {e}");
+    }
 }
 
 #[cfg(test)]
@@ -133,4 +283,73 @@ mod tests {
         assert_eq!(solve_part1::<Day02>(SAMPLE), "1985");
         assert_eq!(solve_part2::<Day02>(SAMPLE), "5DB3");
     }
+
+    #[test]
+    fn test_instructions_for() {
+        for (keypad, code) in [
+            (Keypad::default(), "1985"),
+            (Keypad::default(), "5555"),
+            (Keypad::new(KEYPAD2, 2, 0), "5DB3"),
+            (Keypad::new(KEYPAD2, 2, 0), "D1A9"),
+        ] {
+            let (row, col) = (keypad.row, keypad.col);
+            let lines = keypad.instructions_for(code).unwrap();
+            let mut punching = Keypad::new(keypad.keypad, row, col);
+            let typed = lines
+                .iter()
+                .map(|line| punching.punch_in(line))
+                .collect::<String>();
+            assert_eq!(typed, code);
+        }
+
+        let lines = Keypad::default().instructions_for("1985").unwrap();
+        assert_eq!(
+            lines.iter().map(|l| l.len()).collect::<Vec<_>>(),
+            vec![2, 4, 1, 1]
+        );
+        assert!(Keypad::default().instructions_for("A").is_err());
+    }
+
+    #[test]
+    fn test_all_instructions_for() {
+        let lines = Keypad::default().all_instructions_for("19").unwrap();
+        let lines = lines
+            .iter()
+            .map(|alternatives| {
+                alternatives
+                    .iter()
+                    .map(|a| format_directions(a))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                vec!["UL", "LU"],
+                vec!["DDRR", "DRDR", "DRRD", "RDDR", "RDRD", "RRDD"]
+            ]
+        );
+
+        // the diamond keypad has a single way around the corners
+        let lines = Keypad::new(KEYPAD2, 2, 0)
+            .all_instructions_for("1")
+            .unwrap();
+        assert_eq!(lines[0].len(), 2);
+    }
+
+    #[test]
+    fn test_unreachable_key() {
+        const SPLIT: &[&[Option<char>]] = &[
+            &[Some('1'), Some('2'), None, Some('3')],
+            &[Some('4'), None, None, Some('5')],
+        ];
+        let keypad = Keypad::new(SPLIT, 0, 0);
+        assert_eq!(keypad.instructions_for("241").unwrap().len(), 3);
+        assert!(keypad.instructions_for("3").is_err());
+        assert!(keypad.all_instructions_for("25").is_err());
+        // starting on the other side
+        let keypad = Keypad::new(SPLIT, 1, 3);
+        assert_eq!(keypad.instructions_for("35").unwrap().len(), 2);
+        assert!(keypad.all_instructions_for("1").is_err());
+    }
 }
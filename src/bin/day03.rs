@@ -1,10 +1,11 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 
 use aoc_helpers::{
     anyhow,
-    scaffold::{solve, Problem, VecFromLines},
+    scaffold::{solve, Problem, TrimAndParse},
 };
 
+#[derive(Clone, Copy, Debug)]
 struct Triangle {
     sides: [usize; 3],
 }
@@ -23,45 +24,313 @@ impl FromStr for Triangle {
     }
 }
 
+#[derive(Clone, Debug)]
+struct Triangles {
+    rows: Vec<Triangle>,
+}
+
+impl FromStr for Triangles {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| {
+                line.parse::<Triangle>()
+                    .map_err(|e| anyhow::anyhow!("Line {}: {}", idx + 1, e))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { rows })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TriangleKind {
+    Impossible,
+    Degenerate,
+    Equilateral,
+    Isosceles,
+    Right,
+    Scalene,
+}
+
 impl Triangle {
     fn is_possible(&self) -> bool {
         self.sides[0] < self.sides[1] + self.sides[2]
             && self.sides[1] < self.sides[0] + self.sides[2]
             && self.sides[2] < self.sides[0] + self.sides[1]
     }
+
+    fn classify(&self) -> TriangleKind {
+        let mut sides = self.sides;
+        sides.sort_unstable();
+        let [a, b, c] = sides;
+        if a + b < c {
+            TriangleKind::Impossible
+        } else if a + b == c {
+            TriangleKind::Degenerate
+        } else if a == c {
+            TriangleKind::Equilateral
+        } else if a == b || b == c {
+            TriangleKind::Isosceles
+        } else if a * a + b * b == c * c {
+            TriangleKind::Right
+        } else {
+            TriangleKind::Scalene
+        }
+    }
 }
 
-struct Day03;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct TriangleStats {
+    impossible: usize,
+    degenerate: usize,
+    equilateral: usize,
+    isosceles: usize,
+    right: usize,
+    scalene: usize,
+}
+
+impl TriangleStats {
+    fn add(&mut self, triangle: &Triangle) {
+        *match triangle.classify() {
+            TriangleKind::Impossible => &mut self.impossible,
+            TriangleKind::Degenerate => &mut self.degenerate,
+            TriangleKind::Equilateral => &mut self.equilateral,
+            TriangleKind::Isosceles => &mut self.isosceles,
+            TriangleKind::Right => &mut self.right,
+            TriangleKind::Scalene => &mut self.scalene,
+        } += 1;
+    }
+
+    fn possible(&self) -> usize {
+        self.equilateral + self.isosceles + self.right + self.scalene
+    }
+
+    fn total(&self) -> usize {
+        self.impossible + self.degenerate + self.possible()
+    }
+}
+
+impl FromIterator<Triangle> for TriangleStats {
+    fn from_iter<T: IntoIterator<Item = Triangle>>(iter: T) -> Self {
+        let mut stats = Self::default();
+        for triangle in iter {
+            stats.add(&triangle);
+        }
+        stats
+    }
+}
+
+impl Display for TriangleStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "total:       {}", self.total())?;
+        writeln!(f, "possible:    {}", self.possible())?;
+        writeln!(f, "  equilateral: {}", self.equilateral)?;
+        writeln!(f, "  isosceles:   {}", self.isosceles)?;
+        writeln!(f, "  right:       {}", self.right)?;
+        writeln!(f, "  scalene:     {}", self.scalene)?;
+        writeln!(f, "degenerate:  {}", self.degenerate)?;
+        write!(f, "impossible:  {}", self.impossible)
+    }
+}
+
+trait Columns {
+    type Cell: Copy;
+
+    fn columns(&self) -> &[Self::Cell];
+}
+
+impl Columns for Triangle {
+    type Cell = usize;
 
-fn repack_for_part2(mut input: &[Triangle]) -> Vec<Triangle> {
-    assert!(input.len() % 3 == 0);
-    let mut result = Vec::new();
-    while !input.is_empty() {
-        for i in 0..3 {
-            result.push(Triangle {
-                sides: [input[0].sides[i], input[1].sides[i], input[2].sides[i]],
-            });
+    fn columns(&self) -> &[Self::Cell] {
+        &self.sides
+    }
+}
+
+// reads columns of consecutive groups of N rows (column by column, group by group)
+struct ColumnGroups<'a, T, const N: usize> {
+    rows: &'a [T],
+    row: usize,
+    column: usize,
+}
+
+impl<'a, T, const N: usize> ColumnGroups<'a, T, N> {
+    fn new(rows: &'a [T]) -> Self {
+        Self {
+            rows,
+            row: 0,
+            column: 0,
         }
-        input = input.split_at(3).1;
     }
-    result
+
+    fn fail(&mut self, err: anyhow::Error) -> Option<anyhow::Result<[T::Cell; N]>>
+    where
+        T: Columns,
+    {
+        self.rows = &[];
+        Some(Err(err))
+    }
 }
 
+impl<'a, T: Columns, const N: usize> Iterator for ColumnGroups<'a, T, N> {
+    type Item = anyhow::Result<[T::Cell; N]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rows.is_empty() {
+                return None;
+            }
+            if self.rows.len() < N {
+                let err = anyhow::anyhow!(
+                    "Incomplete group at row {}: {} rows instead of {}",
+                    self.row,
+                    self.rows.len(),
+                    N
+                );
+                return self.fail(err);
+            }
+            let (group, rest) = self.rows.split_at(N);
+            let width = group[0].columns().len();
+            if let Some(idx) = group.iter().position(|r| r.columns().len() != width) {
+                let err = anyhow::anyhow!(
+                    "Row {} has {} columns instead of {}",
+                    self.row + idx,
+                    group[idx].columns().len(),
+                    width
+                );
+                return self.fail(err);
+            }
+            if self.column < width {
+                let cells = std::array::from_fn(|idx| group[idx].columns()[self.column]);
+                self.column += 1;
+                return Some(Ok(cells));
+            }
+            // group done, move on to the next one
+            self.rows = rest;
+            self.row += N;
+            self.column = 0;
+        }
+    }
+}
+
+// stats of triangles read by columns, fails on an incomplete last group
+fn column_stats(rows: &[Triangle]) -> anyhow::Result<TriangleStats> {
+    ColumnGroups::<_, 3>::new(rows)
+        .map(|sides| sides.map(|sides| Triangle { sides }))
+        .collect()
+}
+
+struct Day03;
+
 impl Problem for Day03 {
-    type Input = VecFromLines<Triangle>;
+    type Input = TrimAndParse<Triangles>;
     type Part1 = usize;
     type Part2 = usize;
 
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
-        input.iter().filter(|t| t.is_possible()).count()
+        input.rows.iter().filter(|t| t.is_possible()).count()
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        let repacked = repack_for_part2(input);
-        Self::solve_part1(&repacked)
+        column_stats(&input.rows)
+            .unwrap_or_else(|e| panic!("Triangles can't be read by columns: {e}"))
+            .possible()
     }
 }
 
 fn main() {
-    solve::<Day03>(include_str!("../../inputs/day03.txt"));
+    let input = include_str!("../../inputs/day03.txt");
+    match std::env::args().nth(1).as_deref() {
+        Some("stats") => {
+            let triangles = match input.trim().parse::<Triangles>() {
+                Ok(triangles) => triangles,
+                Err(e) => return eprintln!("{e}"),
+            };
+            println!("By rows:");
+            println!(
+                "{}",
+                triangles.rows.iter().copied().collect::<TriangleStats>()
+            );
+            println!("By columns:");
+            match column_stats(&triangles.rows) {
+                Ok(stats) => println!("{stats}"),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+        _ => solve::<Day03>(input),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc_helpers::scaffold::solve_part2;
+
+    const SAMPLE: &str =
+        "101 301 501\n102 302 502\n103 303 503\n201 401 601\n202 402 602\n203 403 603";
+
+    #[test]
+    fn test_sample_part2() {
+        assert_eq!(solve_part2::<Day03>(SAMPLE), 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "Incomplete group at row 6: 2 rows instead of 3")]
+    fn test_incomplete_part2() {
+        solve_part2::<Day03>(&format!("{SAMPLE}\n3 4 5\n3 4 5"));
+    }
+
+    #[test]
+    fn test_column_stats() {
+        let triangles = format!("{SAMPLE}\n3 4 5\n3 4 5")
+            .parse::<Triangles>()
+            .unwrap();
+        assert_eq!(
+            column_stats(&triangles.rows).unwrap_err().to_string(),
+            "Incomplete group at row 6: 2 rows instead of 3"
+        );
+        let stats = column_stats(&triangles.rows[..6]).unwrap();
+        assert_eq!(stats.total(), 6);
+        assert_eq!(stats.possible(), 6);
+
+        let err = "3 4 5\n3 4".parse::<Triangles>().unwrap_err();
+        assert_eq!(err.to_string(), "Line 2: not enough numbers");
+    }
+
+    #[test]
+    fn test_column_groups() {
+        let rows = [[1, 2], [3, 4], [5, 6]]
+            .into_iter()
+            .map(|[a, b]| Triangle { sides: [a, b, 0] })
+            .collect::<Vec<_>>();
+        let mut groups = ColumnGroups::<_, 2>::new(&rows);
+        assert_eq!(groups.next().unwrap().unwrap(), [1, 3]);
+        assert_eq!(groups.next().unwrap().unwrap(), [2, 4]);
+        assert_eq!(groups.next().unwrap().unwrap(), [0, 0]);
+        assert!(groups.next().unwrap().is_err());
+        assert!(groups.next().is_none());
+    }
+
+    #[test]
+    fn test_classify() {
+        let kind = |sides| Triangle { sides }.classify();
+        assert_eq!(kind([5, 10, 25]), TriangleKind::Impossible);
+        assert_eq!(kind([5, 10, 15]), TriangleKind::Degenerate);
+        assert_eq!(kind([7, 7, 7]), TriangleKind::Equilateral);
+        assert_eq!(kind([7, 3, 7]), TriangleKind::Isosceles);
+        assert_eq!(kind([5, 3, 4]), TriangleKind::Right);
+        assert_eq!(kind([4, 5, 6]), TriangleKind::Scalene);
+
+        let stats = [[5, 10, 25], [3, 4, 5], [6, 8, 10], [2, 2, 3]]
+            .into_iter()
+            .map(|sides| Triangle { sides })
+            .collect::<TriangleStats>();
+        assert_eq!(stats.total(), 4);
+        assert_eq!(stats.possible(), 3);
+        assert_eq!(stats.right, 2);
+        assert_eq!(stats.isosceles, 1);
+    }
 }
@@ -1,9 +1,10 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
 use aoc_helpers::{
     anyhow,
     scaffold::{solve, Problem, VecFromLines},
 };
+use regex::Regex;
 
 struct Day04;
 
@@ -23,6 +24,30 @@ fn shift_chr(c: char, times: usize) -> char {
 }
 
 impl Room {
+    fn encrypt(name: &str, sector_id: usize) -> anyhow::Result<Self> {
+        if let Some(c) = name.chars().find(|c| *c != ' ' && !c.is_ascii_lowercase()) {
+            return Err(anyhow::anyhow!("Can't encrypt character: {:?}", c));
+        }
+        // shifting by the remainder of a full cycle undoes the decryption shift
+        let times = 26 - sector_id % 26;
+        let mut room = Self {
+            name: name
+                .chars()
+                .map(|c| if c == ' ' { '-' } else { shift_chr(c, times) })
+                .collect(),
+            sector_id,
+            checksum: String::new(),
+        };
+        room.checksum = room.calculate_checksum();
+        if room.checksum.len() < 5 {
+            return Err(anyhow::anyhow!(
+                "Name needs at least 5 different letters for a checksum: {}",
+                name
+            ));
+        }
+        Ok(room)
+    }
+
     fn calculate_checksum(&self) -> String {
         let mut counts: HashMap<char, usize> = HashMap::new();
         for c in self.name.chars().filter(char::is_ascii_alphabetic) {
@@ -50,6 +75,37 @@ impl Room {
     }
 }
 
+impl Display for Room {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}[{}]", self.name, self.sector_id, self.checksum)
+    }
+}
+
+enum Query {
+    Keyword(String),
+    Regex(Regex),
+}
+
+impl Query {
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Query::Keyword(keyword) => name.contains(keyword.as_str()),
+            Query::Regex(re) => re.is_match(name),
+        }
+    }
+}
+
+fn search_rooms<'a>(
+    rooms: &'a [Room],
+    query: &'a Query,
+) -> impl Iterator<Item = (&'a Room, String)> + 'a {
+    rooms
+        .iter()
+        .filter(|r| r.is_real())
+        .map(|r| (r, r.decrypt_name()))
+        .filter(|(_, name)| query.matches(name))
+}
+
 impl FromStr for Room {
     type Err = anyhow::Error;
 
@@ -88,16 +144,47 @@ impl Problem for Day04 {
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        input
-            .iter()
-            .find(|r| r.is_real() && r.decrypt_name().starts_with("north"))
-            .expect("there should be a room")
-            .sector_id
+        let query = Query::Regex(Regex::new("^north").unwrap());
+        let (room, _) = search_rooms(input, &query)
+            .next()
+            .expect("there should be a room");
+        room.sector_id
     }
 }
 
 fn main() {
-    solve::<Day04>(include_str!("../../inputs/day04.txt"));
+    let input = include_str!("../../inputs/day04.txt");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let query = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["encrypt", name, sector_id] => {
+            let sector_id = sector_id
+                .parse::<usize>()
+                .expect("sector id should be a number");
+            match Room::encrypt(name, sector_id) {
+                Ok(room) => println!("{room}"),
+                Err(e) => eprintln!("{e}"),
+            }
+            return;
+        }
+        ["search", "--regex", pattern] => {
+            Query::Regex(Regex::new(pattern).expect("pattern should be a valid regex"))
+        }
+        ["search", keyword] => Query::Keyword(keyword.to_string()),
+        _ => return solve::<Day04>(input),
+    };
+    let rooms = input
+        .lines()
+        .map(str::parse::<Room>)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("input should parse");
+    for (room, name) in search_rooms(&rooms, &query) {
+        println!("{}: {}", room.sector_id, name);
+    }
 }
 
 #[cfg(test)]
@@ -127,4 +214,40 @@ mod tests {
         let r = "qzmt-zixmtkozy-ivhz-343[abcde]".parse::<Room>().unwrap();
         assert_eq!(r.decrypt_name(), "very encrypted name");
     }
+
+    #[test]
+    fn test_encrypt() {
+        let r = Room::encrypt("very encrypted name", 343).unwrap();
+        assert_eq!(r.name, "qzmt-zixmtkozy-ivhz");
+        assert_eq!(r.decrypt_name(), "very encrypted name");
+
+        let parsed = r.to_string().parse::<Room>().unwrap();
+        assert_eq!(parsed.name, r.name);
+        assert_eq!(parsed.sector_id, 343);
+        assert!(parsed.is_real());
+
+        assert!(Room::encrypt("aaa bbb", 10).is_err());
+        assert!(Room::encrypt("Not lowercase", 10).is_err());
+    }
+
+    #[test]
+    fn test_search_rooms() {
+        let rooms = [
+            "qzmt-zixmtkozy-ivhz-343[zimth]",
+            "qzmt-zixmtkozy-ivhz-343[decoy]",
+            SAMPLE1,
+        ]
+        .into_iter()
+        .map(|s| s.parse::<Room>().unwrap())
+        .collect::<Vec<_>>();
+
+        let query = Query::Keyword("encrypted".to_owned());
+        let found = search_rooms(&rooms, &query)
+            .map(|(r, name)| (r.sector_id, name))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![(343, "very encrypted name".to_owned())]);
+
+        let query = Query::Regex(Regex::new("^(very|ttttt)").unwrap());
+        assert_eq!(search_rooms(&rooms, &query).count(), 2);
+    }
 }
@@ -1,31 +1,134 @@
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    cmp::Reverse,
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
 
 use aoc_helpers::scaffold::{solve, Problem, RowsOfChars};
 
 struct Day06;
 
-fn most_common<I: Iterator>(iter: I) -> Option<I::Item>
-where
-    I::Item: Eq + Hash,
-{
-    let mut counts: HashMap<I::Item, usize> = HashMap::new();
-    for item in iter {
-        *counts.entry(item).or_default() += 1;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TieOrder {
+    Alphabetical,
+    FirstSeen,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pick {
+    Most,
+    Least,
+}
+
+#[derive(Clone, Debug)]
+struct Histogram<T> {
+    // kept in first seen order
+    counts: Vec<(T, usize)>,
+}
+
+impl<T> Default for Histogram<T> {
+    fn default() -> Self {
+        Self { counts: Vec::new() }
+    }
+}
+
+impl<T: Copy + Ord> Histogram<T> {
+    fn add(&mut self, item: T) {
+        if let Some((_, count)) = self.counts.iter_mut().find(|(i, _)| *i == item) {
+            *count += 1;
+        } else {
+            self.counts.push((item, 1));
+        }
+    }
+
+    fn sorted(&self, order: TieOrder) -> Vec<(T, usize)> {
+        let mut sorted = self.counts.clone();
+        match order {
+            TieOrder::Alphabetical => sorted.sort_unstable_by_key(|&(i, c)| (Reverse(c), i)),
+            TieOrder::FirstSeen => sorted.sort_by_key(|&(_, c)| Reverse(c)),
+        }
+        sorted
+    }
+
+    fn candidates(&self, pick: Pick) -> impl Iterator<Item = T> + '_ {
+        let counts = self.counts.iter().map(|(_, c)| *c);
+        let target = match pick {
+            Pick::Most => counts.max(),
+            Pick::Least => counts.min(),
+        };
+        self.counts
+            .iter()
+            .filter(move |(_, c)| Some(*c) == target)
+            .map(|(i, _)| *i)
+    }
+
+    fn pick(&self, pick: Pick, order: TieOrder) -> Option<T> {
+        match order {
+            TieOrder::Alphabetical => self.candidates(pick).min(),
+            TieOrder::FirstSeen => self.candidates(pick).next(),
+        }
+    }
+
+    fn is_tied(&self, pick: Pick) -> bool {
+        self.candidates(pick).nth(1).is_some()
     }
-    let max_count = counts.values().copied().max();
-    max_count.and_then(|m| counts.into_iter().find(|(_, v)| *v == m).map(|(k, _)| k))
 }
 
-fn least_common<I: Iterator>(iter: I) -> Option<I::Item>
-where
-    I::Item: Eq + Hash,
-{
-    let mut counts: HashMap<I::Item, usize> = HashMap::new();
-    for item in iter {
-        *counts.entry(item).or_default() += 1;
+#[derive(Clone, Debug)]
+struct ColumnFrequencies<T> {
+    columns: Vec<Histogram<T>>,
+}
+
+impl<T> Default for ColumnFrequencies<T> {
+    fn default() -> Self {
+        Self {
+            columns: Vec::new(),
+        }
+    }
+}
+
+impl<T: Copy + Ord> ColumnFrequencies<T> {
+    fn add_row<I: IntoIterator<Item = T>>(&mut self, row: I) {
+        for (idx, item) in row.into_iter().enumerate() {
+            if idx == self.columns.len() {
+                self.columns.push(Default::default());
+            }
+            self.columns[idx].add(item);
+        }
+    }
+
+    fn pick(&self, pick: Pick, order: TieOrder) -> Option<Vec<T>> {
+        self.columns.iter().map(|h| h.pick(pick, order)).collect()
+    }
+
+    fn tied_positions(&self, pick: Pick) -> Vec<usize> {
+        self.columns
+            .iter()
+            .enumerate()
+            .filter(|(_, h)| h.is_tied(pick))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+}
+
+impl ColumnFrequencies<char> {
+    fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut result = Self::default();
+        for line in reader.lines() {
+            result.add_row(line?.trim().chars());
+        }
+        Ok(result)
+    }
+}
+
+impl<T: Copy + Ord, R: IntoIterator<Item = T>> FromIterator<R> for ColumnFrequencies<T> {
+    fn from_iter<I: IntoIterator<Item = R>>(iter: I) -> Self {
+        let mut result = Self::default();
+        for row in iter {
+            result.add_row(row);
+        }
+        result
     }
-    let min_count = counts.values().copied().min();
-    min_count.and_then(|m| counts.into_iter().find(|(_, v)| *v == m).map(|(k, _)| k))
 }
 
 impl Problem for Day06 {
@@ -34,22 +137,71 @@ impl Problem for Day06 {
     type Part2 = String;
 
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
-        (0..input[0].len())
-            .map(|idx| most_common(input.iter().map(|s| s[idx])))
-            .collect::<Option<String>>()
+        input
+            .iter()
+            .map(|row| row.iter().copied())
+            .collect::<ColumnFrequencies<char>>()
+            .pick(Pick::Most, TieOrder::Alphabetical)
             .expect("there should be a solution")
+            .into_iter()
+            .collect()
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        (0..input[0].len())
-            .map(|idx| least_common(input.iter().map(|s| s[idx])))
-            .collect::<Option<String>>()
+        input
+            .iter()
+            .map(|row| row.iter().copied())
+            .collect::<ColumnFrequencies<char>>()
+            .pick(Pick::Least, TieOrder::Alphabetical)
             .expect("there should be a solution")
+            .into_iter()
+            .collect()
+    }
+}
+
+fn print_histograms<R: BufRead>(reader: R, order: TieOrder) -> io::Result<()> {
+    let frequencies = ColumnFrequencies::from_reader(reader)?;
+    for (idx, histogram) in frequencies.columns.iter().enumerate() {
+        let counts = histogram
+            .sorted(order)
+            .into_iter()
+            .map(|(c, count)| format!("{c}:{count}"))
+            .collect::<Vec<_>>();
+        println!("{idx}: {}", counts.join(" "));
     }
+    println!(
+        "tied most common positions: {:?}",
+        frequencies.tied_positions(Pick::Most)
+    );
+    println!(
+        "tied least common positions: {:?}",
+        frequencies.tied_positions(Pick::Least)
+    );
+    Ok(())
 }
 
 fn main() {
-    solve::<Day06>(include_str!("../../inputs/day06.txt"));
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let order = if args.iter().any(|a| a == "--first-seen") {
+        TieOrder::FirstSeen
+    } else {
+        TieOrder::Alphabetical
+    };
+    let args = args
+        .iter()
+        .map(String::as_str)
+        .filter(|a| !a.starts_with("--"))
+        .collect::<Vec<_>>();
+    match args.as_slice() {
+        ["histograms"] => {
+            print_histograms(io::stdin().lock(), order).expect("stdin should be readable")
+        }
+        ["histograms", path] => {
+            let file = File::open(path).expect("file should be readable");
+            print_histograms(BufReader::new(file), order).expect("file should be readable")
+        }
+        _ => solve::<Day06>(include_str!("../../inputs/day06.txt")),
+    }
 }
 
 #[cfg(test)]
@@ -64,4 +216,31 @@ mod tests {
         assert_eq!(solve_part1::<Day06>(SAMPLE), "easter");
         assert_eq!(solve_part2::<Day06>(SAMPLE), "advent");
     }
+
+    #[test]
+    fn test_ties() {
+        let frequencies = ColumnFrequencies::from_reader("ba\nab\nca\n".as_bytes()).unwrap();
+        assert_eq!(
+            frequencies.pick(Pick::Most, TieOrder::Alphabetical),
+            Some(vec!['a', 'a'])
+        );
+        assert_eq!(
+            frequencies.pick(Pick::Most, TieOrder::FirstSeen),
+            Some(vec!['b', 'a'])
+        );
+        assert_eq!(
+            frequencies.pick(Pick::Least, TieOrder::Alphabetical),
+            Some(vec!['a', 'b'])
+        );
+        assert_eq!(
+            frequencies.pick(Pick::Least, TieOrder::FirstSeen),
+            Some(vec!['b', 'b'])
+        );
+        assert_eq!(frequencies.tied_positions(Pick::Most), vec![0]);
+        assert_eq!(frequencies.tied_positions(Pick::Least), vec![0]);
+        assert_eq!(
+            frequencies.columns[0].sorted(TieOrder::FirstSeen),
+            vec![('b', 1), ('a', 1), ('c', 1)]
+        );
+    }
 }
@@ -35,6 +35,14 @@ impl Default for SeqType {
 struct Sequence {
     seq_type: SeqType,
     seq: Vec<u8>,
+    start: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Witness {
+    seq_type: SeqType,
+    position: usize,
+    text: String,
 }
 
 impl Sequence {
@@ -56,6 +64,51 @@ impl Sequence {
             }
         })
     }
+
+    fn witness(&self, offset: usize, bytes: &[u8]) -> Witness {
+        Witness {
+            seq_type: self.seq_type,
+            position: self.start + offset,
+            text: String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+
+    fn abbas(&self) -> impl Iterator<Item = Witness> + '_ {
+        self.seq.windows(4).enumerate().filter_map(|(offset, w)| {
+            if w[0] == w[3] && w[1] == w[2] && w[0] != w[1] {
+                Some(self.witness(offset, w))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn abas(&self) -> impl Iterator<Item = Witness> + '_ {
+        self.seq.windows(3).enumerate().filter_map(|(offset, w)| {
+            if w[0] == w[2] && w[0] != w[1] {
+                Some(self.witness(offset, w))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Explanation {
+    supernet_abbas: Vec<Witness>,
+    hypernet_abbas: Vec<Witness>,
+    aba_bab_pairs: Vec<(Witness, Witness)>,
+}
+
+impl Explanation {
+    fn supports_tls(&self) -> bool {
+        !self.supernet_abbas.is_empty() && self.hypernet_abbas.is_empty()
+    }
+
+    fn supports_ssl(&self) -> bool {
+        !self.aba_bab_pairs.is_empty()
+    }
 }
 
 struct Ip(Vec<Sequence>);
@@ -90,6 +143,31 @@ impl Ip {
         }
         false
     }
+
+    fn explain(&self) -> Explanation {
+        let mut explanation = Explanation::default();
+        for abba in self.0.iter().flat_map(|s| s.abbas()) {
+            match abba.seq_type {
+                SeqType::Normal => explanation.supernet_abbas.push(abba),
+                SeqType::Hypernet => explanation.hypernet_abbas.push(abba),
+            }
+        }
+        let (abas, babs): (Vec<_>, Vec<_>) = self
+            .0
+            .iter()
+            .flat_map(|s| s.abas())
+            .partition(|w| w.seq_type == SeqType::Normal);
+        for aba in &abas {
+            let aba_bytes = aba.text.as_bytes();
+            for bab in &babs {
+                let bab_bytes = bab.text.as_bytes();
+                if aba_bytes[0] == bab_bytes[1] && aba_bytes[1] == bab_bytes[0] {
+                    explanation.aba_bab_pairs.push((aba.clone(), bab.clone()));
+                }
+            }
+        }
+        explanation
+    }
 }
 
 impl FromStr for Ip {
@@ -97,24 +175,59 @@ impl FromStr for Ip {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut seqs = Vec::new();
-        let mut seq_type = Default::default();
+        let mut open_brackets = Vec::new();
         let mut seq = Vec::new();
-        for b in s.as_bytes() {
+        let mut start = 0;
+        let seq_type = |open_brackets: &Vec<usize>| {
+            if open_brackets.is_empty() {
+                SeqType::Normal
+            } else {
+                SeqType::Hypernet
+            }
+        };
+        for (idx, b) in s.bytes().enumerate() {
             match b {
                 b'[' => {
-                    seqs.push(Sequence { seq, seq_type });
+                    seqs.push(Sequence {
+                        seq,
+                        seq_type: seq_type(&open_brackets),
+                        start,
+                    });
                     seq = Vec::new();
-                    seq_type = SeqType::Hypernet;
+                    start = idx + 1;
+                    open_brackets.push(idx);
                 }
                 b']' => {
-                    seqs.push(Sequence { seq, seq_type });
+                    seqs.push(Sequence {
+                        seq,
+                        seq_type: seq_type(&open_brackets),
+                        start,
+                    });
                     seq = Vec::new();
-                    seq_type = SeqType::Normal;
+                    start = idx + 1;
+                    open_brackets.pop().ok_or_else(|| {
+                        anyhow::anyhow!("Unmatched ']' at position {}: {}", idx, s)
+                    })?;
+                }
+                b'a'..=b'z' => seq.push(b),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Unexpected character {:?} at position {}: {}",
+                        b as char,
+                        idx,
+                        s
+                    ))
                 }
-                _ => seq.push(*b),
             }
         }
-        seqs.push(Sequence { seq, seq_type });
+        if let Some(idx) = open_brackets.last() {
+            return Err(anyhow::anyhow!("Unclosed '[' at position {}: {}", idx, s));
+        }
+        seqs.push(Sequence {
+            seq,
+            seq_type: SeqType::Normal,
+            start,
+        });
         Ok(Self(seqs))
     }
 }
@@ -133,6 +246,123 @@ impl Problem for Day07 {
     }
 }
 
+fn print_explanation(address: &str) {
+    let ip = match address.parse::<Ip>() {
+        Ok(ip) => ip,
+        Err(e) => return eprintln!("{e}"),
+    };
+    let explanation = ip.explain();
+    let describe = |w: &Witness| format!("{} at {} ({:?})", w.text, w.position, w.seq_type);
+    println!("TLS: {}", explanation.supports_tls());
+    for abba in explanation
+        .supernet_abbas
+        .iter()
+        .chain(explanation.hypernet_abbas.iter())
+    {
+        println!("  ABBA {}", describe(abba));
+    }
+    println!("SSL: {}", explanation.supports_ssl());
+    for (aba, bab) in &explanation.aba_bab_pairs {
+        println!("  ABA {} with BAB {}", describe(aba), describe(bab));
+    }
+}
+
 fn main() {
-    solve::<Day07>(include_str!("../../inputs/day07.txt"));
+    match std::env::args().skip(1).collect::<Vec<_>>().as_slice() {
+        [cmd, address] if cmd == "explain" => print_explanation(address),
+        _ => solve::<Day07>(include_str!("../../inputs/day07.txt")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explain(s: &str) -> Explanation {
+        s.parse::<Ip>().unwrap().explain()
+    }
+
+    #[test]
+    fn test_sample() {
+        for (s, tls) in [
+            ("abba[mnop]qrst", true),
+            ("abcd[bddb]xyyx", false),
+            ("aaaa[qwer]tyui", false),
+            ("ioxxoj[asdfgh]zxcvbn", true),
+        ] {
+            assert_eq!(s.parse::<Ip>().unwrap().supports_tls(), tls);
+            assert_eq!(explain(s).supports_tls(), tls);
+        }
+        for (s, ssl) in [
+            ("aba[bab]xyz", true),
+            ("xyx[xyx]xyx", false),
+            ("aaa[kek]eke", true),
+            ("zazbz[bzb]cdb", true),
+        ] {
+            assert_eq!(s.parse::<Ip>().unwrap().supports_ssl(), ssl);
+            assert_eq!(explain(s).supports_ssl(), ssl);
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let explanation = explain("abcd[bddb]xyyx");
+        assert_eq!(
+            explanation.supernet_abbas,
+            vec![Witness {
+                seq_type: SeqType::Normal,
+                position: 10,
+                text: "xyyx".to_owned(),
+            }]
+        );
+        assert_eq!(
+            explanation.hypernet_abbas,
+            vec![Witness {
+                seq_type: SeqType::Hypernet,
+                position: 5,
+                text: "bddb".to_owned(),
+            }]
+        );
+
+        let explanation = explain("zazbz[bzb]cdb");
+        let pairs = explanation
+            .aba_bab_pairs
+            .iter()
+            .map(|(aba, bab)| {
+                (
+                    aba.text.as_str(),
+                    aba.position,
+                    bab.text.as_str(),
+                    bab.position,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, vec![("zbz", 2, "bzb", 6)]);
+    }
+
+    #[test]
+    fn test_nested() {
+        let ip = "abba[cd[xyyx]ef]gh".parse::<Ip>().unwrap();
+        assert_eq!(
+            ip.0.iter().map(|s| s.seq_type).collect::<Vec<_>>(),
+            vec![
+                SeqType::Normal,
+                SeqType::Hypernet,
+                SeqType::Hypernet,
+                SeqType::Hypernet,
+                SeqType::Normal
+            ]
+        );
+        assert!(!ip.supports_tls());
+        assert_eq!(ip.explain().hypernet_abbas[0].position, 8);
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!("abc]def".parse::<Ip>().is_err());
+        assert!("abc[def".parse::<Ip>().is_err());
+        assert!("abc[d[e]f".parse::<Ip>().is_err());
+        assert!("abc[def]]".parse::<Ip>().is_err());
+        assert!("abc def".parse::<Ip>().is_err());
+    }
 }
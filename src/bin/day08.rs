@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{fmt::Display, fs, io::Write, path::Path, str::FromStr, thread, time::Duration};
 
 use aoc_helpers::{
     anyhow,
@@ -7,6 +7,9 @@ use aoc_helpers::{
 
 struct Day08;
 
+const DISPLAY_ROWS: usize = 6;
+const DISPLAY_COLS: usize = 50;
+
 #[derive(Clone, Copy, Debug)]
 enum Command {
     On { cols: usize, rows: usize },
@@ -53,12 +56,109 @@ impl FromStr for Command {
     }
 }
 
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Command::On { cols, rows } => write!(f, "rect {cols}x{rows}"),
+            Command::RotateRow { row, by } => write!(f, "rotate row y={row} by {by}"),
+            Command::RotateColumn { col, by } => write!(f, "rotate column x={col} by {by}"),
+        }
+    }
+}
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 6;
+
+const FONT: &[(char, [&str; GLYPH_HEIGHT])] = &[
+    ('A', [".##..", "#..#.", "#..#.", "####.", "#..#.", "#..#."]),
+    ('B', ["###..", "#..#.", "###..", "#..#.", "#..#.", "###.."]),
+    ('C', [".##..", "#..#.", "#....", "#....", "#..#.", ".##.."]),
+    ('E', ["####.", "#....", "###..", "#....", "#....", "####."]),
+    ('F', ["####.", "#....", "###..", "#....", "#....", "#...."]),
+    ('G', [".##..", "#..#.", "#....", "#.##.", "#..#.", ".###."]),
+    ('H', ["#..#.", "#..#.", "####.", "#..#.", "#..#.", "#..#."]),
+    ('I', [".###.", "..#..", "..#..", "..#..", "..#..", ".###."]),
+    ('J', ["..##.", "...#.", "...#.", "...#.", "#..#.", ".##.."]),
+    ('K', ["#..#.", "#.#..", "##...", "#.#..", "#.#..", "#..#."]),
+    ('L', ["#....", "#....", "#....", "#....", "#....", "####."]),
+    ('O', [".##..", "#..#.", "#..#.", "#..#.", "#..#.", ".##.."]),
+    ('P', ["###..", "#..#.", "#..#.", "###..", "#....", "#...."]),
+    ('R', ["###..", "#..#.", "#..#.", "###..", "#.#..", "#..#."]),
+    ('S', [".###.", "#....", "#....", ".##..", "...#.", "###.."]),
+    ('U', ["#..#.", "#..#.", "#..#.", "#..#.", "#..#.", ".##.."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####.", "...#.", "..#..", ".#...", "#....", "####."]),
+    (' ', [".....", ".....", ".....", ".....", ".....", "....."]),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct UnknownGlyph {
+    row: usize,
+    col: usize,
+    rendered: String,
+}
+
+impl std::fmt::Display for UnknownGlyph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unknown glyph at row {}, column {}:{}",
+            self.row, self.col, self.rendered
+        )
+    }
+}
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+// rows are packed into words, column `c` is bit `c % 64` of word `c / 64`
 #[derive(Clone, Debug)]
-struct RotDisplay(Vec<Vec<bool>>);
+struct RotDisplay {
+    rows: usize,
+    cols: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+    // reused when rotating rows longer than a single word
+    scratch: Vec<u64>,
+}
+
+fn low_bits(len: usize) -> u64 {
+    if len >= WORD_BITS {
+        u64::MAX
+    } else {
+        (1 << len) - 1
+    }
+}
+
+// reads `len` (up to 64) bits starting at `start`
+fn read_bits(words: &[u64], start: usize, len: usize) -> u64 {
+    let (idx, offset) = (start / WORD_BITS, start % WORD_BITS);
+    let mut bits = words[idx] >> offset;
+    if offset + len > WORD_BITS {
+        bits |= words[idx + 1] << (WORD_BITS - offset);
+    }
+    bits & low_bits(len)
+}
+
+// reads `len` (up to 64) bits starting at `start` wrapping around at `cols`
+fn read_bits_wrapping(words: &[u64], cols: usize, start: usize, len: usize) -> u64 {
+    let first_len = len.min(cols - start);
+    let mut bits = read_bits(words, start, first_len);
+    if first_len < len {
+        bits |= read_bits(words, 0, len - first_len) << first_len;
+    }
+    bits
+}
 
 impl RotDisplay {
     fn new(rows: usize, cols: usize) -> Self {
-        Self(vec![vec![false; cols]; rows])
+        let words_per_row = cols.div_ceil(WORD_BITS);
+        Self {
+            rows,
+            cols,
+            words_per_row,
+            words: vec![0; rows * words_per_row],
+            scratch: Vec::new(),
+        }
     }
 
     fn execute_all(&mut self, cmds: &[Command]) {
@@ -67,44 +167,196 @@ impl RotDisplay {
         }
     }
 
+    fn row_words(&self, row: usize) -> &[u64] {
+        &self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
+    fn row_words_mut(&mut self, row: usize) -> &mut [u64] {
+        &mut self.words[row * self.words_per_row..(row + 1) * self.words_per_row]
+    }
+
     fn execute(&mut self, cmd: &Command) {
         match *cmd {
             Command::On { cols, rows } => {
-                for row in &mut self.0[0..rows] {
-                    for p in &mut row[0..cols] {
-                        *p = true;
+                let cols = cols.min(self.cols);
+                for row in 0..rows.min(self.rows) {
+                    for (idx, word) in self.row_words_mut(row).iter_mut().enumerate() {
+                        *word |= low_bits(cols.saturating_sub(idx * WORD_BITS));
                     }
                 }
             }
             Command::RotateRow { row, by } => {
-                let row_len = self.0[row].len();
-                self.0[row].rotate_right(by % row_len);
+                if row >= self.rows || self.cols == 0 {
+                    return;
+                }
+                let cols = self.cols;
+                let by = by % cols;
+                if by == 0 {
+                    return;
+                }
+                if self.words_per_row == 1 {
+                    let word = &mut self.words[row];
+                    *word = ((*word << by) | (*word >> (cols - by))) & low_bits(cols);
+                } else {
+                    let mut scratch = std::mem::take(&mut self.scratch);
+                    scratch.clear();
+                    scratch.extend_from_slice(self.row_words(row));
+                    for (idx, word) in self.row_words_mut(row).iter_mut().enumerate() {
+                        let start = idx * WORD_BITS;
+                        let len = WORD_BITS.min(cols - start);
+                        *word = read_bits_wrapping(&scratch, cols, (start + cols - by) % cols, len);
+                    }
+                    self.scratch = scratch;
+                }
             }
             Command::RotateColumn { col, by } => {
-                let col_len = self.0.len();
-                let mut column = self.0.iter().map(|row| row[col]).collect::<Vec<bool>>();
-                column.rotate_right(by % col_len);
-                for (p, rotated) in self
-                    .0
-                    .iter_mut()
-                    .map(|row| &mut row[col])
-                    .zip(column.into_iter())
-                {
-                    *p = rotated;
+                if col >= self.cols || self.rows == 0 {
+                    return;
+                }
+                let rows = self.rows;
+                let by = by % rows;
+                if by == 0 {
+                    return;
+                }
+                // follow the cycles of the rotation moving one pixel at a time
+                let cycles = gcd(rows, by);
+                for cycle_start in 0..cycles {
+                    let mut carried = self.get(cycle_start, col);
+                    let mut row = cycle_start;
+                    loop {
+                        row = (row + by) % rows;
+                        let replaced = self.get(row, col);
+                        self.set(row, col, carried);
+                        carried = replaced;
+                        if row == cycle_start {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn from_text(text: &str) -> Option<Self> {
+        let mut display = Self::new(GLYPH_HEIGHT, GLYPH_WIDTH * text.chars().count());
+        for (idx, chr) in text.chars().enumerate() {
+            let (_, glyph) = FONT.iter().find(|(c, _)| *c == chr.to_ascii_uppercase())?;
+            for (row, line) in glyph.iter().enumerate() {
+                for (dx, b) in line.bytes().enumerate() {
+                    display.set(row, idx * GLYPH_WIDTH + dx, b == b'#');
+                }
+            }
+        }
+        Some(display)
+    }
+
+    fn resized(&self, rows: usize, cols: usize) -> anyhow::Result<Self> {
+        check_size(rows, cols)?;
+        let mut display = Self::new(rows, cols);
+        for row in 0..rows {
+            for col in 0..cols {
+                display.set(row, col, self.get(row, col));
+            }
+        }
+        Ok(display)
+    }
+
+    fn execute_recorded(&mut self, cmds: &[Command]) -> Vec<Self> {
+        cmds.iter()
+            .map(|cmd| {
+                self.execute(cmd);
+                self.clone()
+            })
+            .collect()
+    }
+
+    fn get(&self, row: usize, col: usize) -> bool {
+        if row < self.rows && col < self.cols {
+            let word = self.words[row * self.words_per_row + col / WORD_BITS];
+            word & (1 << (col % WORD_BITS)) != 0
+        } else {
+            false
+        }
+    }
+
+    fn set(&mut self, row: usize, col: usize, val: bool) {
+        assert!(row < self.rows && col < self.cols);
+        let word = &mut self.words[row * self.words_per_row + col / WORD_BITS];
+        if val {
+            *word |= 1 << (col % WORD_BITS);
+        } else {
+            *word &= !(1 << (col % WORD_BITS));
+        }
+    }
+
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn cols(&self) -> usize {
+        self.cols
+    }
+
+    fn row_pixels(&self, row: usize) -> impl Iterator<Item = bool> + '_ {
+        (0..self.cols).map(move |col| self.get(row, col))
+    }
+
+    fn read_glyph(&self, row: usize, col: usize) -> Result<char, UnknownGlyph> {
+        FONT.iter()
+            .find(|(_, glyph)| {
+                glyph.iter().enumerate().all(|(dy, line)| {
+                    line.bytes()
+                        .enumerate()
+                        .all(|(dx, b)| (b == b'#') == self.get(row + dy, col + dx))
+                })
+            })
+            .map(|(chr, _)| *chr)
+            .ok_or_else(|| {
+                let mut rendered = String::new();
+                for dy in 0..GLYPH_HEIGHT {
+                    rendered.push('\n');
+                    for dx in 0..GLYPH_WIDTH {
+                        rendered.push(if self.get(row + dy, col + dx) {
+                            '#'
+                        } else {
+                            '.'
+                        });
+                    }
+                }
+                UnknownGlyph { row, col, rendered }
+            })
+    }
+
+    fn read_text(&self) -> Result<String, Vec<UnknownGlyph>> {
+        let mut text = String::new();
+        let mut unknown = Vec::new();
+        for row in (0..self.rows()).step_by(GLYPH_HEIGHT) {
+            if row > 0 {
+                text.push('\n');
+            }
+            for col in (0..self.cols()).step_by(GLYPH_WIDTH) {
+                match self.read_glyph(row, col) {
+                    Ok(chr) => text.push(chr),
+                    Err(glyph) => unknown.push(glyph),
                 }
             }
         }
+        if unknown.is_empty() {
+            Ok(text)
+        } else {
+            Err(unknown)
+        }
     }
 
     fn count_pixels(&self) -> usize {
-        self.0.iter().flat_map(|r| r.iter()).filter(|b| **b).count()
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
     }
 
     fn render(&self) -> String {
         let mut result = String::new();
-        for row in self.0.iter().map(|row| {
-            row.iter()
-                .map(|p| if *p { '#' } else { '.' })
+        for row in (0..self.rows).map(|row| {
+            self.row_pixels(row)
+                .map(|p| if p { '#' } else { '.' })
                 .collect::<String>()
         }) {
             result.push('\n');
@@ -112,6 +364,260 @@ impl RotDisplay {
         }
         result
     }
+
+    fn to_pbm(&self) -> String {
+        let mut result = format!("P1\n{} {}\n", self.cols(), self.rows());
+        for row in 0..self.rows {
+            let pixels = self
+                .row_pixels(row)
+                .map(|p| if p { "1" } else { "0" })
+                .collect::<Vec<_>>();
+            result.push_str(&pixels.join(" "));
+            result.push('\n');
+        }
+        result
+    }
+
+    fn to_pgm(&self) -> String {
+        let mut result = format!("P2\n{} {}\n255\n", self.cols(), self.rows());
+        for row in 0..self.rows {
+            let pixels = self
+                .row_pixels(row)
+                .map(|p| if p { "255" } else { "0" })
+                .collect::<Vec<_>>();
+            result.push_str(&pixels.join(" "));
+            result.push('\n');
+        }
+        result
+    }
+}
+
+impl PartialEq for RotDisplay {
+    fn eq(&self, other: &Self) -> bool {
+        self.rows == other.rows && self.cols == other.cols && self.words == other.words
+    }
+}
+
+impl Eq for RotDisplay {}
+
+// rotations wrap around so there has to be something to wrap
+fn check_size(rows: usize, cols: usize) -> anyhow::Result<()> {
+    if rows == 0 || cols == 0 {
+        Err(anyhow::anyhow!(
+            "Display should have at least one row and column, got {}x{}",
+            cols,
+            rows
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn parse_size(s: &str) -> anyhow::Result<(usize, usize)> {
+    let (cols, rows) = s
+        .split_once('x')
+        .ok_or_else(|| anyhow::anyhow!("Expected COLSxROWS: {}", s))?;
+    let (rows, cols) = (rows.parse::<usize>()?, cols.parse::<usize>()?);
+    check_size(rows, cols)?;
+    Ok((rows, cols))
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+impl FromStr for RotDisplay {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.chars()
+                    .map(|c| match c {
+                        '#' => Ok(true),
+                        '.' => Ok(false),
+                        _ => Err(anyhow::anyhow!("Unexpected pixel: {:?}", c)),
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err(anyhow::anyhow!("All rows should have the same length"));
+        }
+        let (height, width) = (rows.len(), rows.first().map(Vec::len).unwrap_or_default());
+        check_size(height, width)?;
+        let mut display = Self::new(height, width);
+        for (row, pixels) in rows.into_iter().enumerate() {
+            for (col, p) in pixels.into_iter().enumerate() {
+                display.set(row, col, p);
+            }
+        }
+        Ok(display)
+    }
+}
+
+// builds the target column by column in column 0 and shifts the rows right after each one
+// (rows are shifted lazily, only when their pixels are about to pass through column 0)
+fn synthesize(target: &RotDisplay) -> anyhow::Result<Vec<Command>> {
+    let (rows, cols) = (target.rows(), target.cols());
+    check_size(rows, cols)?;
+    let mut current = RotDisplay::new(rows, cols);
+    let mut cmds = Vec::new();
+    let mut emit = |cmd: Command, current: &mut RotDisplay| {
+        current.execute(&cmd);
+        cmds.push(cmd);
+    };
+    let mut pending = vec![0; rows];
+    for step in 0..cols {
+        let target_col = (cols - step) % cols;
+        let lit_rows = (0..rows)
+            .filter(|row| target.get(*row, target_col))
+            .collect::<Vec<_>>();
+        if !lit_rows.is_empty() {
+            // make column 0 empty and in sync for the rows about to be lit
+            for (row, p) in pending.iter_mut().enumerate() {
+                let by = *p % cols;
+                if by != 0 && (current.get(row, 0) || lit_rows.contains(&row)) {
+                    emit(Command::RotateRow { row, by }, &mut current);
+                    *p = 0;
+                }
+            }
+
+            // contiguous runs of lit rows as (start, len)
+            let mut runs: Vec<(usize, usize)> = Vec::new();
+            for row in lit_rows {
+                match runs.last_mut() {
+                    Some((start, len)) if *start + *len == row => *len += 1,
+                    _ => runs.push((row, 1)),
+                }
+            }
+
+            // light the runs from the bottom one, pushing them down the column as we go
+            for idx in (0..runs.len()).rev() {
+                let (start, len) = runs[idx];
+                emit(Command::On { cols: 1, rows: len }, &mut current);
+                let next_start = if idx > 0 { runs[idx - 1].0 } else { 0 };
+                if start > next_start {
+                    emit(
+                        Command::RotateColumn {
+                            col: 0,
+                            by: start - next_start,
+                        },
+                        &mut current,
+                    );
+                }
+            }
+        }
+        for p in pending.iter_mut() {
+            *p += 1;
+        }
+    }
+    for (row, p) in pending.into_iter().enumerate() {
+        let by = p % cols;
+        if by != 0 && (0..cols).any(|col| current.get(row, col)) {
+            emit(Command::RotateRow { row, by }, &mut current);
+        }
+    }
+    debug_assert_eq!(&current, target);
+    Ok(cmds)
+}
+
+// GIF encoding with a 2 colour palette, the LZW stream gets reset often enough
+// to keep the codes 3 bits long which avoids implementing the dictionary at all
+const GIF_MIN_CODE_SIZE: u8 = 2;
+const GIF_CLEAR_CODE: u16 = 4;
+const GIF_END_CODE: u16 = 5;
+const GIF_CODE_WIDTH: u32 = 3;
+const GIF_CODES_PER_CLEAR: usize = 2;
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u32) {
+        self.acc |= u32::from(code) << self.bits;
+        self.bits += width;
+        while self.bits >= 8 {
+            self.bytes.push((self.acc & 0xff) as u8);
+            self.acc >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push((self.acc & 0xff) as u8);
+        }
+        self.bytes
+    }
+}
+
+fn gif_frame_data(display: &RotDisplay, scale: usize) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    let pixels = (0..display.rows() * scale).flat_map(|y| {
+        (0..display.cols() * scale).map(move |x| u16::from(display.get(y / scale, x / scale)))
+    });
+    for (idx, pixel) in pixels.enumerate() {
+        if idx % GIF_CODES_PER_CLEAR == 0 {
+            writer.write(GIF_CLEAR_CODE, GIF_CODE_WIDTH);
+        }
+        writer.write(pixel, GIF_CODE_WIDTH);
+    }
+    writer.write(GIF_END_CODE, GIF_CODE_WIDTH);
+    writer.finish()
+}
+
+fn frames_to_gif(frames: &[RotDisplay], scale: usize, delay: Duration) -> Vec<u8> {
+    let (rows, cols) = frames
+        .first()
+        .map(|f| (f.rows(), f.cols()))
+        .unwrap_or_default();
+    let width = u16::try_from(cols * scale).expect("image should be small enough");
+    let height = u16::try_from(rows * scale).expect("image should be small enough");
+    let delay = u16::try_from(delay.as_millis() / 10).unwrap_or(u16::MAX);
+
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend(width.to_le_bytes());
+    gif.extend(height.to_le_bytes());
+    // global colour table with 2 entries: off and on
+    gif.extend([0x80, 0, 0]);
+    gif.extend([0x10, 0x10, 0x10, 0xff, 0xff, 0xff]);
+    // loop forever
+    gif.extend([0x21, 0xff, 0x0b]);
+    gif.extend(b"NETSCAPE2.0");
+    gif.extend([0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    for frame in frames {
+        // graphic control extension with the frame delay
+        gif.extend([0x21, 0xf9, 0x04, 0x00]);
+        gif.extend(delay.to_le_bytes());
+        gif.extend([0x00, 0x00]);
+        // image descriptor covering the whole screen
+        gif.push(0x2c);
+        gif.extend([0, 0, 0, 0]);
+        gif.extend(width.to_le_bytes());
+        gif.extend(height.to_le_bytes());
+        gif.push(0);
+        gif.push(GIF_MIN_CODE_SIZE);
+        for block in gif_frame_data(frame, scale).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend(block);
+        }
+        gif.push(0);
+    }
+    gif.push(0x3b);
+    gif
 }
 
 impl Problem for Day08 {
@@ -120,18 +626,315 @@ impl Problem for Day08 {
     type Part2 = String;
 
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
-        let mut display = RotDisplay::new(6, 50);
+        let mut display = RotDisplay::new(DISPLAY_ROWS, DISPLAY_COLS);
         display.execute_all(input);
         display.count_pixels()
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        let mut display = RotDisplay::new(6, 50);
+        let mut display = RotDisplay::new(DISPLAY_ROWS, DISPLAY_COLS);
         display.execute_all(input);
-        display.render()
+        display.read_text().unwrap_or_else(|unknown| {
+            // fall back to the raw rendering so it can still be read by a human
+            let mut result = String::new();
+            for glyph in unknown {
+                result.push_str(&format!("\n{glyph}"));
+            }
+            result.push_str(&display.render());
+            result
+        })
+    }
+}
+
+fn replay(cmds: &[Command], frames: &[RotDisplay], delay: Duration) {
+    for (cmd, frame) in cmds.iter().zip(frames) {
+        // clear the terminal and move the cursor to the top left corner
+        println!("\x1b[2J\x1b[H{cmd}{}", frame.render());
+        std::io::stdout()
+            .flush()
+            .expect("stdout should be writable");
+        thread::sleep(delay);
+    }
+}
+
+fn export_images(frames: &[RotDisplay], dir: &Path, extension: &str) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (idx, frame) in frames.iter().enumerate() {
+        let image = match extension {
+            "pbm" => frame.to_pbm(),
+            _ => frame.to_pgm(),
+        };
+        fs::write(dir.join(format!("frame{idx:04}.{extension}")), image)?;
     }
+    Ok(())
+}
+
+fn parse_option<T: FromStr>(args: &[String], name: &str) -> Option<T> {
+    args.iter()
+        .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+        .map(|val| {
+            val.parse::<T>()
+                .unwrap_or_else(|_| panic!("Invalid value for {name}: {val}"))
+        })
 }
 
 fn main() {
-    solve::<Day08>(include_str!("../../inputs/day08.txt"));
+    let input = include_str!("../../inputs/day08.txt");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let size = match parse_option::<String>(&args, "--size")
+        .map(|size| parse_size(&size))
+        .transpose()
+    {
+        Ok(size) => size,
+        Err(e) => return eprintln!("Invalid size: {e}"),
+    };
+    let delay = Duration::from_millis(parse_option(&args, "--delay").unwrap_or(50));
+    let scale = parse_option(&args, "--scale").unwrap_or(4);
+    let positional = args
+        .iter()
+        .map(String::as_str)
+        .filter(|a| !a.starts_with("--"))
+        .collect::<Vec<_>>();
+    let target = match positional.as_slice() {
+        [] => return solve::<Day08>(input),
+        ["synthesize", text] => {
+            Some(RotDisplay::from_text(text).expect("text should use only known letters"))
+        }
+        ["synthesize-image", path] => Some(
+            fs::read_to_string(path)
+                .expect("image should be readable")
+                .parse::<RotDisplay>()
+                .expect("image should consist of '#' and '.' rows"),
+        ),
+        _ => None,
+    };
+    if let Some(target) = target {
+        let cmds = match size {
+            Some((rows, cols)) => target.resized(rows, cols),
+            None => Ok(target),
+        }
+        .and_then(|target| synthesize(&target));
+        match cmds {
+            Ok(cmds) => {
+                for cmd in cmds {
+                    println!("{cmd}");
+                }
+            }
+            Err(e) => eprintln!("{e}"),
+        }
+        return;
+    }
+
+    let cmds = input
+        .lines()
+        .map(str::parse::<Command>)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("input should parse");
+    let (rows, cols) = size.unwrap_or((DISPLAY_ROWS, DISPLAY_COLS));
+    let frames = RotDisplay::new(rows, cols).execute_recorded(&cmds);
+    match positional.as_slice() {
+        ["replay"] => replay(&cmds, &frames, delay),
+        [ext @ ("pbm" | "pgm"), dir] => {
+            export_images(&frames, Path::new(dir), ext).expect("images should be written")
+        }
+        ["gif", path] => {
+            fs::write(path, frames_to_gif(&frames, scale, delay)).expect("gif should be written")
+        }
+        _ => eprintln!("Unknown command: {}", positional.join(" ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let mut display = RotDisplay::new(3, 7);
+        display.execute_all(&[
+            "rect 3x2".parse().unwrap(),
+            "rotate column x=1 by 1".parse().unwrap(),
+            "rotate row y=0 by 4".parse().unwrap(),
+            "rotate column x=1 by 1".parse().unwrap(),
+        ]);
+        assert_eq!(display.render(), "\n.#..#.#\n#.#....\n.#.....");
+        assert_eq!(display.count_pixels(), 6);
+    }
+
+    #[test]
+    fn test_execute_recorded() {
+        let cmds = ["rect 3x2", "rotate column x=1 by 1", "rotate row y=0 by 4"]
+            .into_iter()
+            .map(|s| s.parse::<Command>().unwrap())
+            .collect::<Vec<_>>();
+        let frames = RotDisplay::new(3, 7).execute_recorded(&cmds);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].render(), "\n###....\n###....\n.......");
+        assert_eq!(frames[2].render(), "\n....#.#\n###....\n.#.....");
+        assert_eq!(
+            frames[0].to_pbm(),
+            "P1\n7 3\n1 1 1 0 0 0 0\n1 1 1 0 0 0 0\n0 0 0 0 0 0 0\n"
+        );
+        assert!(frames[0]
+            .to_pgm()
+            .starts_with("P2\n7 3\n255\n255 255 255 0"));
+
+        // commands out of the display are clipped
+        let mut display = RotDisplay::new(2, 2);
+        display.execute_all(&cmds);
+        assert_eq!(display.count_pixels(), 4);
+    }
+
+    #[test]
+    fn test_gif() {
+        let frames = [RotDisplay::new(1, 3)];
+        let gif = frames_to_gif(&frames, 1, Duration::from_millis(100));
+        assert!(gif.starts_with(b"GIF89a\x03\x00\x01\x00"));
+        assert_eq!(gif.last(), Some(&0x3b));
+        // clear, 0, 0, clear, 0, end packed in 3 bit codes
+        assert_eq!(gif_frame_data(&frames[0], 1), vec![0x04, 0x88, 0x02]);
+    }
+
+    #[test]
+    fn test_read_text() {
+        let display = [
+            ".##..####.#...#",
+            "#..#.#....#...#",
+            "#....###...#.#.",
+            "#....#......#..",
+            "#..#.#......#..",
+            ".##..#......#..",
+        ]
+        .join("\n")
+        .parse::<RotDisplay>()
+        .unwrap();
+        assert_eq!(display.read_text(), Ok("CFY".to_owned()));
+
+        let display = [
+            "#....#....",
+            ".#...#....",
+            "..#..#....",
+            "...#.#....",
+            "....##....",
+            ".....####.",
+        ]
+        .join("\n")
+        .parse::<RotDisplay>()
+        .unwrap();
+        let unknown = display.read_text().unwrap_err();
+        assert_eq!(unknown.len(), 1);
+        assert_eq!((unknown[0].row, unknown[0].col), (0, 0));
+        assert_eq!(
+            unknown[0].rendered,
+            "\n#....\n.#...\n..#..\n...#.\n....#\n....."
+        );
+    }
+
+    fn assert_synthesizes(target: &RotDisplay) {
+        let program = synthesize(target)
+            .unwrap()
+            .iter()
+            .map(|cmd| cmd.to_string())
+            .collect::<Vec<_>>();
+        let cmds = program
+            .iter()
+            .map(|line| line.parse::<Command>().unwrap())
+            .collect::<Vec<_>>();
+        let mut display = RotDisplay::new(target.rows(), target.cols());
+        display.execute_all(&cmds);
+        assert_eq!(&display, target);
+    }
+
+    #[test]
+    fn test_synthesize() {
+        let text = RotDisplay::from_text("HELLO").unwrap();
+        assert_synthesizes(&text);
+        assert_synthesizes(&text.resized(8, 30).unwrap());
+        assert_synthesizes(&RotDisplay::new(6, 50));
+        assert_synthesizes(&"#".parse().unwrap());
+
+        // pseudo random bitmaps
+        let mut seed = 42u32;
+        for (rows, cols) in [(1, 1), (3, 7), (6, 50), (10, 3), (5, 5)] {
+            let mut display = RotDisplay::new(rows, cols);
+            for row in 0..rows {
+                for col in 0..cols {
+                    seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                    display.set(row, col, (seed >> 16) & 0xffff < 0x5555);
+                }
+            }
+            assert_synthesizes(&display);
+        }
+    }
+
+    #[test]
+    fn test_zero_size() {
+        let text = RotDisplay::from_text("HI").unwrap();
+        assert!(text.resized(0, 10).is_err());
+        assert!(text.resized(6, 0).is_err());
+        assert!(synthesize(&RotDisplay::from_text("").unwrap()).is_err());
+        assert!("".parse::<RotDisplay>().is_err());
+        assert!(parse_size("0x6").is_err());
+        assert!(parse_size("50x0").is_err());
+        assert_eq!(parse_size("50x6").unwrap(), (6, 50));
+    }
+
+    #[test]
+    fn test_large_display() {
+        // compare against a naive model, including rows spanning multiple words
+        for (rows, cols) in [(7, 150), (5, 64), (6, 50)] {
+            assert_matches_model(rows, cols);
+        }
+    }
+
+    fn assert_matches_model(rows: usize, cols: usize) {
+        let mut display = RotDisplay::new(rows, cols);
+        let mut model = vec![vec![false; cols]; rows];
+        let mut seed = 7u32;
+        let mut next = |max: usize| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as usize % max
+        };
+        for _ in 0..500 {
+            let cmd = match next(3) {
+                0 => Command::On {
+                    cols: next(cols + 10),
+                    rows: next(rows + 2),
+                },
+                1 => Command::RotateRow {
+                    row: next(rows),
+                    by: next(3 * cols),
+                },
+                _ => Command::RotateColumn {
+                    col: next(cols),
+                    by: next(3 * rows),
+                },
+            };
+            display.execute(&cmd);
+            match cmd {
+                Command::On { cols, rows } => {
+                    for row in model.iter_mut().take(rows) {
+                        for p in row.iter_mut().take(cols) {
+                            *p = true;
+                        }
+                    }
+                }
+                Command::RotateRow { row, by } => model[row].rotate_right(by % cols),
+                Command::RotateColumn { col, by } => {
+                    let mut column = model.iter().map(|row| row[col]).collect::<Vec<_>>();
+                    column.rotate_right(by % rows);
+                    for (row, p) in model.iter_mut().zip(column) {
+                        row[col] = p;
+                    }
+                }
+            }
+            for (row, pixels) in model.iter().enumerate() {
+                assert!(display.row_pixels(row).eq(pixels.iter().copied()), "{cmd}");
+            }
+        }
+        assert_eq!(
+            display.count_pixels(),
+            model.iter().flatten().filter(|p| **p).count()
+        );
+    }
 }
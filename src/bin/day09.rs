@@ -1,5 +1,9 @@
-use aoc_helpers::scaffold::{solve, Problem};
-use regex::Regex;
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::str::FromStr;
+
+use aoc_helpers::anyhow;
+use aoc_helpers::scaffold::{solve, Problem, TrimAndParse};
 
 struct Day09;
 
@@ -43,38 +47,70 @@ impl CharGroup {
     }
 }
 
+#[derive(Debug)]
 struct CharGroupSeq {
     groups: Vec<CharGroup>,
+    raw: String,
 }
 
-impl CharGroupSeq {
-    fn new(raw: &str) -> Self {
-        lazy_static::lazy_static! {
-            static ref GROUP_RE: Regex = Regex::new(r"[A-Z]+|\(\d+x\d+\)").unwrap();
-            static ref REPEATED_RE: Regex = Regex::new(r"\((\d+)x(\d+)\)").unwrap();
-        };
+impl FromStr for CharGroupSeq {
+    type Err = anyhow::Error;
 
-        let groups = GROUP_RE
-            .find_iter(raw)
-            .map(|mtch| {
-                if mtch.as_str().starts_with('(') {
-                    let caps = REPEATED_RE
-                        .captures(mtch.as_str())
-                        .expect("already matched");
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let data = s.as_bytes();
+        let mut groups = Vec::new();
+        // markers before this are repeated data of an enclosing marker, in v1 they're plain
+        // characters and in v2 they're cut at the end of the enclosing data
+        let mut enclosed_until = 0;
+        let mut pos = 0;
+        while pos < data.len() {
+            let start = pos;
+            let group = match data[pos] {
+                b'A'..=b'Z' => {
+                    while pos < data.len() && data[pos].is_ascii_uppercase() {
+                        pos += 1;
+                    }
+                    CharGroup::Normal(pos - start)
+                }
+                b'(' => {
+                    let (chars, times, after) =
+                        parse_marker(data, pos, data.len()).ok_or_else(|| {
+                            let snippet = s[pos..].chars().take(12).collect::<String>();
+                            anyhow::anyhow!("Malformed marker at position {}: {}", pos, snippet)
+                        })?;
+                    if pos >= enclosed_until && chars > data.len() - after {
+                        return Err(anyhow::anyhow!(
+                            "Marker at position {} repeats {} chars but only {} follow",
+                            pos,
+                            chars,
+                            data.len() - after
+                        ));
+                    }
+                    if pos >= enclosed_until {
+                        enclosed_until = after + chars;
+                    }
+                    pos = after;
                     CharGroup::Repeated {
-                        chars: caps.get(1).unwrap().as_str().parse::<usize>().unwrap(),
-                        times: caps.get(2).unwrap().as_str().parse::<usize>().unwrap(),
-                        raw_len: mtch.range().len(),
+                        chars,
+                        times,
+                        raw_len: after - start,
                     }
-                } else {
-                    CharGroup::Normal(mtch.range().len())
                 }
-            })
-            .collect();
-
-        Self { groups }
+                _ => {
+                    let c = s[pos..].chars().next().expect("pos is inside the string");
+                    return Err(anyhow::anyhow!("Unexpected {:?} at position {}", c, pos));
+                }
+            };
+            groups.push(group);
+        }
+        Ok(Self {
+            groups,
+            raw: s.to_owned(),
+        })
     }
+}
 
+impl CharGroupSeq {
     fn decompressed_len(&self) -> usize {
         let mut len = 0;
         let mut consuming = 0;
@@ -98,154 +134,627 @@ impl CharGroupSeq {
         }
         len
     }
+}
 
-    fn denormalised(&self) -> Self {
-        let mut groups = Vec::new();
-        for group in &self.groups {
-            match *group {
-                CharGroup::Normal(count) => {
-                    for _ in 0..count {
-                        groups.push(CharGroup::Normal(1));
-                    }
-                }
-                CharGroup::Repeated { .. } => groups.push(*group),
-            }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Version {
+    // markers inside repeated data are plain characters
+    V1,
+    // markers inside repeated data are expanded too
+    V2,
+}
+
+// parses "(AxB)" at `pos` (ending before `end`), returns (chars, times, position after the marker)
+fn parse_marker(data: &[u8], pos: usize, end: usize) -> Option<(usize, usize, usize)> {
+    let parse_num = |mut pos: usize| {
+        let start = pos;
+        let mut num = 0usize;
+        while pos < end && data[pos].is_ascii_digit() {
+            num = num
+                .checked_mul(10)?
+                .checked_add((data[pos] - b'0') as usize)?;
+            pos += 1;
+        }
+        if pos > start {
+            Some((num, pos))
+        } else {
+            None
         }
-        Self { groups }
+    };
+    if pos >= end || data[pos] != b'(' {
+        return None;
     }
+    let (chars, pos) = parse_num(pos + 1)?;
+    if pos >= end || data[pos] != b'x' {
+        return None;
+    }
+    let (times, pos) = parse_num(pos + 1)?;
+    if pos >= end || data[pos] != b')' {
+        return None;
+    }
+    Some((chars, times, pos + 1))
 }
 
-// TODO: test
-fn split_after_raw_len(
-    groups: &[CharGroup],
-    mut len: usize,
-) -> Option<(&[CharGroup], &[CharGroup])> {
-    for (idx, group) in groups.iter().enumerate() {
-        if len < group.len() {
-            return Some(groups.split_at(idx));
+fn decoded_len(
+    data: &[u8],
+    start: usize,
+    end: usize,
+    expand_markers: bool,
+    version: Version,
+) -> u64 {
+    let mut len = 0;
+    let mut pos = start;
+    while pos < end {
+        if data[pos].is_ascii_whitespace() {
+            pos += 1;
+        } else if let Some((chars, times, data_start)) =
+            parse_marker(data, pos, end).filter(|_| expand_markers)
+        {
+            let data_end = data_start.saturating_add(chars).min(end);
+            len += times as u64
+                * decoded_len(data, data_start, data_end, version == Version::V2, version);
+            pos = data_end;
+        } else {
+            len += 1;
+            pos += 1;
         }
-        len -= group.len();
-    }
-    if len == 0 {
-        Some((groups, &[]))
-    } else {
-        None
     }
+    len
 }
 
-fn is_a_tree(groups: &[CharGroup]) -> bool {
-    if let Some((first, rest)) = groups.split_first() {
-        match *first {
-            CharGroup::Normal(_) => is_a_tree(rest),
-            CharGroup::Repeated { chars, .. } => {
-                if let Some((first_group, second_group)) = split_after_raw_len(rest, chars) {
-                    is_a_tree(first_group) && is_a_tree(second_group)
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    start: usize,
+    end: usize,
+    pos: usize,
+    times_left: usize,
+}
+
+// streams the decompressed data keeping only the stack of currently repeated regions
+struct Decoder<'a> {
+    data: &'a [u8],
+    version: Version,
+    stack: Vec<Frame>,
+    position: u64,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(data: &'a [u8], version: Version) -> Self {
+        let mut decoder = Self {
+            data,
+            version,
+            stack: Vec::new(),
+            position: 0,
+        };
+        decoder.rewind();
+        decoder
+    }
+
+    fn rewind(&mut self) {
+        self.stack.clear();
+        self.stack.push(Frame {
+            start: 0,
+            end: self.data.len(),
+            pos: 0,
+            times_left: 1,
+        });
+        self.position = 0;
+    }
+
+    fn total_len(&self) -> u64 {
+        decoded_len(self.data, 0, self.data.len(), true, self.version)
+    }
+
+    fn expands_markers(&self) -> bool {
+        self.version == Version::V2 || self.stack.len() == 1
+    }
+
+    // moves to the next frame to read from (if the current one is finished)
+    // returns false when there's nothing left
+    fn settle(&mut self) -> bool {
+        while let Some(frame) = self.stack.last_mut() {
+            if frame.pos < frame.end {
+                return true;
+            }
+            if frame.times_left > 1 {
+                frame.times_left -= 1;
+                frame.pos = frame.start;
+            } else {
+                self.stack.pop();
+            }
+        }
+        false
+    }
+
+    // skips `skip` output bytes without producing them, whole repetitions are skipped at once
+    // (when the marker gets reached while skipping)
+    fn skip(&mut self, mut skip: u64) {
+        while skip > 0 && self.settle() {
+            let expand_markers = self.expands_markers();
+            let frame = self.stack.last_mut().expect("settled");
+            let pos = frame.pos;
+            if self.data[pos].is_ascii_whitespace() {
+                frame.pos += 1;
+            } else if let Some((chars, times, data_start)) =
+                parse_marker(self.data, pos, frame.end).filter(|_| expand_markers)
+            {
+                let data_end = data_start.saturating_add(chars).min(frame.end);
+                frame.pos = data_end;
+                let region_len = decoded_len(
+                    self.data,
+                    data_start,
+                    data_end,
+                    self.version == Version::V2,
+                    self.version,
+                );
+                let total = region_len * times as u64;
+                if skip >= total {
+                    skip -= total;
+                    self.position += total;
                 } else {
-                    false
+                    let skipped_times = skip / region_len;
+                    skip -= skipped_times * region_len;
+                    self.position += skipped_times * region_len;
+                    self.stack.push(Frame {
+                        start: data_start,
+                        end: data_end,
+                        pos: data_start,
+                        times_left: times - skipped_times as usize,
+                    });
                 }
+            } else {
+                frame.pos += 1;
+                skip -= 1;
+                self.position += 1;
             }
         }
-    } else {
-        true
     }
 }
 
-fn decompressed_tree_len(groups: &[CharGroup]) -> usize {
-    if let Some((first, rest)) = groups.split_first() {
-        match *first {
-            CharGroup::Normal(l) => l + decompressed_tree_len(rest),
-            CharGroup::Repeated { chars, times, .. } => {
-                if let Some((first_group, second_group)) = split_after_raw_len(rest, chars) {
-                    times * decompressed_tree_len(first_group) + decompressed_tree_len(second_group)
-                } else {
-                    panic!("Not a tree!");
+impl<'a> Iterator for Decoder<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.settle() {
+            let expand_markers = self.expands_markers();
+            let frame = self.stack.last_mut().expect("settled");
+            let pos = frame.pos;
+            let b = self.data[pos];
+            if b.is_ascii_whitespace() {
+                frame.pos += 1;
+            } else if let Some((chars, times, data_start)) =
+                parse_marker(self.data, pos, frame.end).filter(|_| expand_markers)
+            {
+                let data_end = data_start.saturating_add(chars).min(frame.end);
+                frame.pos = data_end;
+                if times > 0 && data_end > data_start {
+                    self.stack.push(Frame {
+                        start: data_start,
+                        end: data_end,
+                        pos: data_start,
+                        times_left: times,
+                    });
                 }
+            } else {
+                frame.pos += 1;
+                self.position += 1;
+                return Some(b);
             }
         }
-    } else {
-        // empty
-        0
+        None
+    }
+}
+
+impl<'a> Read for Decoder<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut count = 0;
+        for (b, decoded) in buf.iter_mut().zip(Iterator::by_ref(self)) {
+            *b = decoded;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<'a> Seek for Decoder<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.total_len().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start"))?;
+        // skipping from the start jumps over whole repetitions, which isn't possible
+        // from the middle of one
+        self.rewind();
+        self.skip(target);
+        Ok(self.position)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Choice {
+    Literal,
+    Marker { unit_len: usize, times: usize },
+}
+
+// z[k] is the length of the common prefix of `text` and `text[k..]`
+fn z_function(text: &[u8]) -> Vec<usize> {
+    let n = text.len();
+    let mut z = vec![0; n];
+    if n > 0 {
+        z[0] = n;
+    }
+    let (mut left, mut right) = (0, 0);
+    for k in 1..n {
+        if k < right {
+            z[k] = z[k - left].min(right - k);
+        }
+        while k + z[k] < n && text[z[k]] == text[k + z[k]] {
+            z[k] += 1;
+        }
+        if k + z[k] > right {
+            left = k;
+            right = k + z[k];
+        }
     }
+    z
+}
+
+fn digits(mut num: usize) -> usize {
+    let mut count = 1;
+    while num >= 10 {
+        num /= 10;
+        count += 1;
+    }
+    count
+}
+
+// finds the shortest encoding of `text` built from literals and markers over consecutive repeats,
+// with v2 the repeated unit gets compressed recursively
+fn compress_bytes(text: &[u8], version: Version, memo: &mut HashMap<Vec<u8>, Vec<u8>>) -> Vec<u8> {
+    if let Some(encoded) = memo.get(text) {
+        return encoded.clone();
+    }
+    let n = text.len();
+    let mut best = vec![(0, Choice::Literal); n + 1];
+    let mut units = HashMap::new();
+    for pos in (0..n).rev() {
+        best[pos] = (best[pos + 1].0 + 1, Choice::Literal);
+        let z = z_function(&text[pos..]);
+        for unit_len in 1..=(n - pos) / 2 {
+            let max_times = 1 + z[unit_len] / unit_len;
+            if max_times < 2 {
+                continue;
+            }
+            let unit_cost = match version {
+                Version::V1 => unit_len,
+                Version::V2 => {
+                    let unit = &text[pos..pos + unit_len];
+                    let encoded = compress_bytes(unit, version, memo);
+                    let cost = encoded.len();
+                    units.insert((pos, unit_len), encoded);
+                    cost
+                }
+            };
+            for times in 2..=max_times {
+                let cost = digits(unit_cost)
+                    + digits(times)
+                    + 3
+                    + unit_cost
+                    + best[pos + unit_len * times].0;
+                if cost < best[pos].0 {
+                    best[pos] = (cost, Choice::Marker { unit_len, times });
+                }
+            }
+        }
+    }
+
+    let mut encoded = Vec::with_capacity(best[0].0);
+    let mut pos = 0;
+    while pos < n {
+        match best[pos].1 {
+            Choice::Literal => {
+                encoded.push(text[pos]);
+                pos += 1;
+            }
+            Choice::Marker { unit_len, times } => {
+                let unit = units
+                    .remove(&(pos, unit_len))
+                    .unwrap_or_else(|| text[pos..pos + unit_len].to_vec());
+                encoded.extend(format!("({}x{})", unit.len(), times).bytes());
+                encoded.extend(unit);
+                pos += unit_len * times;
+            }
+        }
+    }
+    memo.insert(text.to_vec(), encoded.clone());
+    encoded
+}
+
+fn compress(text: &str, version: Version) -> anyhow::Result<String> {
+    if let Some((idx, c)) = text.char_indices().find(|(_, c)| !c.is_ascii_uppercase()) {
+        return Err(anyhow::anyhow!(
+            "Only uppercase letters can be compressed, found {:?} at {}",
+            c,
+            idx
+        ));
+    }
+    let encoded = compress_bytes(text.as_bytes(), version, &mut HashMap::new());
+    Ok(String::from_utf8(encoded).expect("markers and uppercase letters are ascii"))
 }
 
 impl Problem for Day09 {
-    type Input = String;
+    type Input = TrimAndParse<CharGroupSeq>;
     type Part1 = usize;
     type Part2 = usize;
 
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
-        CharGroupSeq::new(input).decompressed_len()
+        input.decompressed_len()
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        let char_groups = CharGroupSeq::new(input).denormalised();
-        debug_assert!(is_a_tree(&char_groups.groups));
-        decompressed_tree_len(&char_groups.groups)
+        Decoder::new(input.raw.as_bytes(), Version::V2).total_len() as usize
     }
 }
 
+fn decode(input: &str, version: Version, offset: u64, len: Option<u64>) -> io::Result<()> {
+    let mut decoder = Decoder::new(input.as_bytes(), version);
+    decoder.seek(SeekFrom::Start(offset))?;
+    let mut stdout = io::stdout().lock();
+    match len {
+        Some(len) => io::copy(&mut Read::take(decoder, len), &mut stdout)?,
+        None => io::copy(&mut decoder, &mut stdout)?,
+    };
+    stdout.flush()
+}
+
 fn main() {
-    solve::<Day09>(include_str!("../../inputs/day09.txt"));
+    let input = include_str!("../../inputs/day09.txt");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let option = |name: &str| {
+        args.iter()
+            .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+            .map(|val| val.parse::<u64>().expect("option value should be a number"))
+    };
+    let version = if args.iter().any(|arg| arg == "--v2") {
+        Version::V2
+    } else {
+        Version::V1
+    };
+    let positional = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+    match positional.as_slice() {
+        ["decode"] => decode(
+            input.trim(),
+            version,
+            option("--offset").unwrap_or_default(),
+            option("--len"),
+        )
+        .expect("stdout should be writable"),
+        ["compress", text] => match compress(text, version) {
+            Ok(encoded) => println!("{}", encoded),
+            Err(err) => eprintln!("{}", err),
+        },
+        _ => solve::<Day09>(input),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn decode(s: &str, version: Version) -> String {
+        String::from_utf8(Decoder::new(s.as_bytes(), version).collect()).unwrap()
+    }
+
     #[test]
-    fn test_split_after_raw_len() {
+    fn test_decoder_v1() {
+        for (compressed, decompressed) in [
+            ("ADVENT", "ADVENT"),
+            ("A(1x5)BC", "ABBBBBC"),
+            ("(3x3)XYZ", "XYZXYZXYZ"),
+            ("A(2x2)BCD(2x2)EFG", "ABCBCDEFEFG"),
+            ("(6x1)(1x3)A", "(1x3)A"),
+            ("X(8x2)(3x3)ABCY", "X(3x3)ABC(3x3)ABCY"),
+        ] {
+            assert_eq!(decode(compressed, Version::V1), decompressed);
+            assert_eq!(
+                compressed
+                    .parse::<CharGroupSeq>()
+                    .unwrap()
+                    .decompressed_len() as u64,
+                Decoder::new(compressed.as_bytes(), Version::V1).total_len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_decoder_v2() {
+        assert_eq!(decode("(3x3)XYZ", Version::V2), "XYZXYZXYZ");
         assert_eq!(
-            split_after_raw_len(&[CharGroup::Normal(4),], 4),
-            Some(([CharGroup::Normal(4)].as_slice(), [].as_slice()))
+            decode("X(8x2)(3x3)ABCY", Version::V2),
+            "XABCABCABCABCABCABCY"
         );
+        for (compressed, len) in [
+            ("(27x12)(20x12)(13x14)(7x10)(1x12)A", 241920),
+            (
+                "(25x3)(3x3)ABC(2x3)XY(5x2)PQRSTX(18x9)(3x2)TWO(5x7)SEVEN",
+                445,
+            ),
+        ] {
+            let decoder = Decoder::new(compressed.as_bytes(), Version::V2);
+            assert_eq!(decoder.total_len(), len);
+            assert_eq!(decoder.count() as u64, len);
+        }
+    }
+
+    #[test]
+    fn test_decoder_seek() {
+        for compressed in [
+            "A(2x2)BCD(2x2)EFG",
+            "(25x3)(3x3)ABC(2x3)XY(5x2)PQRSTX(18x9)(3x2)TWO(5x7)SEVEN",
+        ] {
+            for version in [Version::V1, Version::V2] {
+                let full = decode(compressed, version);
+                let mut decoder = Decoder::new(compressed.as_bytes(), version);
+                for offset in (0..=full.len()).rev() {
+                    assert_eq!(
+                        decoder.seek(SeekFrom::Start(offset as u64)).unwrap(),
+                        offset as u64
+                    );
+                    let mut rest = String::new();
+                    decoder.read_to_string(&mut rest).unwrap();
+                    assert_eq!(rest, full[offset..]);
+                }
+            }
+        }
 
+        // huge output: only the requested part gets decoded
+        let compressed = "(27x12)(20x12)(13x14)(7x10)(1x12)B(10x1000000000)(1x3)A";
+        let mut decoder = Decoder::new(compressed.as_bytes(), Version::V2);
+        assert_eq!(decoder.total_len(), 241920 + 3_000_000_000);
+        decoder.seek(SeekFrom::Start(241918)).unwrap();
+        let mut buf = [0; 6];
+        decoder.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"BBAAAA");
+        decoder.seek(SeekFrom::End(-2)).unwrap();
+        assert_eq!(decoder.collect::<Vec<_>>(), b"AA");
+    }
+
+    #[test]
+    fn test_compress() {
+        assert_eq!(compress("ADVENT", Version::V1).unwrap(), "ADVENT");
+        assert_eq!(compress("ABCABCABCABC", Version::V1).unwrap(), "(3x4)ABC");
         assert_eq!(
-            split_after_raw_len(
-                &[
-                    CharGroup::Normal(4),
-                    CharGroup::Repeated {
-                        chars: 4,
-                        times: 2,
-                        raw_len: 5
-                    },
-                    CharGroup::Normal(4),
-                ],
-                4
-            ),
-            Some((
-                [CharGroup::Normal(4)].as_slice(),
-                [
-                    CharGroup::Repeated {
-                        chars: 4,
-                        times: 2,
-                        raw_len: 5
-                    },
-                    CharGroup::Normal(4),
-                ]
-                .as_slice()
-            ))
+            compress("XABABABABABABABABABABY", Version::V2).unwrap(),
+            "X(2x10)ABY"
         );
+        assert_eq!(
+            compress(&"ABABABABC".repeat(5), Version::V1).unwrap(),
+            "(9x5)ABABABABC"
+        );
+        assert_eq!(
+            compress(&"ABABABABC".repeat(5), Version::V2).unwrap(),
+            "(8x5)(2x4)ABC"
+        );
+        assert!(compress("abc", Version::V1).is_err());
+        assert!(compress("A(1x2)B", Version::V2).is_err());
     }
 
     #[test]
-    fn test_is_a_tree() {
-        assert!(is_a_tree(&[CharGroup::Normal(4)]));
-        assert!(is_a_tree(&[
-            CharGroup::Repeated {
-                chars: 4,
-                times: 2,
-                raw_len: 5
-            },
-            CharGroup::Normal(4)
-        ]));
-        assert!(is_a_tree(&[
-            CharGroup::Normal(4),
-            CharGroup::Repeated {
-                chars: 4,
-                times: 2,
-                raw_len: 5
-            },
-            CharGroup::Normal(4),
-        ]));
+    fn test_compress_round_trip() {
+        let mut rng = 0x2016u32;
+        let mut next = || {
+            rng = rng.wrapping_mul(1103515245).wrapping_add(12345);
+            (rng >> 16) as usize
+        };
+        let mut texts = vec![
+            "ADVENT".to_string(),
+            "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_string(),
+            "XYZXYZXYZABABABABXYZXYZXYZABABABAB".repeat(3),
+            "(25x3)(3x3)ABC(2x3)XY(5x2)PQRSTX(18x9)(3x2)TWO(5x7)SEVEN"
+                .replace(|c: char| !c.is_ascii_uppercase(), ""),
+        ];
+        for _ in 0..30 {
+            let alphabet = 1 + next() % 3;
+            let mut text = String::new();
+            while text.len() < 60 {
+                let piece = (0..1 + next() % 4)
+                    .map(|_| (b'A' + (next() % alphabet) as u8) as char)
+                    .collect::<String>();
+                text.push_str(&piece.repeat(1 + next() % 5));
+            }
+            texts.push(text);
+        }
+        for text in texts {
+            for version in [Version::V1, Version::V2] {
+                let compressed = compress(&text, version).unwrap();
+                assert!(compressed.len() <= text.len());
+                assert_eq!(decode(&compressed, version), text);
+                let groups = compressed.parse::<CharGroupSeq>().unwrap();
+                if version == Version::V1 {
+                    assert_eq!(groups.decompressed_len(), text.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (raw, message) in [
+            ("AB(1x2)c", "Unexpected 'c' at position 7"),
+            ("A B", "Unexpected ' ' at position 1"),
+            ("AB)", "Unexpected ')' at position 2"),
+            ("A(1x2B", "Malformed marker at position 1: (1x2B"),
+            ("(x2)A", "Malformed marker at position 0: (x2)A"),
+            (
+                "A(99999999999999999999x2)B",
+                "Malformed marker at position 1",
+            ),
+            (
+                "A(5x2)BCD",
+                "Marker at position 1 repeats 5 chars but only 3 follow",
+            ),
+        ] {
+            let err = raw.parse::<CharGroupSeq>().unwrap_err().to_string();
+            assert!(err.starts_with(message), "{:?} gave {:?}", raw, err);
+        }
+        assert!("".parse::<CharGroupSeq>().unwrap().groups.is_empty());
+    }
+
+    #[test]
+    fn test_groups() {
+        let repeated = |chars, times, raw_len| CharGroup::Repeated {
+            chars,
+            times,
+            raw_len,
+        };
+        for (raw, groups) in [
+            ("ABCD", vec![CharGroup::Normal(4)]),
+            ("(4x2)ABCD", vec![repeated(4, 2, 5), CharGroup::Normal(4)]),
+            (
+                "ABCD(4x2)EFGH(1x3)I",
+                vec![
+                    CharGroup::Normal(4),
+                    repeated(4, 2, 5),
+                    CharGroup::Normal(4),
+                    repeated(1, 3, 5),
+                    CharGroup::Normal(1),
+                ],
+            ),
+            // nested markers
+            (
+                "(9x2)(4x3)ABCDE",
+                vec![repeated(9, 2, 5), repeated(4, 3, 5), CharGroup::Normal(5)],
+            ),
+            // nested marker repeating more than there is, it's just data in v1
+            ("(5x2)(9x9)", vec![repeated(5, 2, 5), repeated(9, 9, 5)]),
+            // marker split by the end of the enclosing data
+            (
+                "(3x2)(1x5)AB",
+                vec![repeated(3, 2, 5), repeated(1, 5, 5), CharGroup::Normal(2)],
+            ),
+        ] {
+            let parsed = raw.parse::<CharGroupSeq>().unwrap();
+            assert_eq!(parsed.groups, groups, "{}", raw);
+            assert_eq!(parsed.raw, raw);
+            assert_eq!(
+                parsed.decompressed_len() as u64,
+                Decoder::new(raw.as_bytes(), Version::V1).total_len(),
+                "{}",
+                raw
+            );
+        }
+        assert_eq!(decode("(5x2)(9x9)", Version::V1), "(9x9)(9x9)");
+        assert_eq!(decode("(5x2)(9x9)", Version::V2), "");
+        assert_eq!(decode("(7x2)(4x3)ABCD", Version::V2), "ABABABABABABCD");
+        assert_eq!(decode("(3x2)(1x5)AB", Version::V2), "(1x(1x5)AB");
+        // only the outer marker has to fit in the data
+        assert!("(5x2)(9x9)A".parse::<CharGroupSeq>().is_ok());
+        assert!("(6x2)(9x9)".parse::<CharGroupSeq>().is_err());
     }
 }
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{self, Write},
+    str::FromStr,
+};
 
 use aoc_helpers::{
     anyhow,
@@ -87,6 +91,15 @@ impl FromStr for Target {
     }
 }
 
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bot(bot) => write!(f, "bot {}", bot),
+            Self::Output(output) => write!(f, "output {}", output),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 struct BotRule {
     low_target: Target,
@@ -133,20 +146,44 @@ impl Default for BotVal {
     }
 }
 
+// a single handoff done by a full bot
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Exchange {
+    bot: usize,
+    low: usize,
+    high: usize,
+    low_target: Target,
+    high_target: Target,
+}
+
+impl fmt::Display for Exchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "bot {} compares {} with {}: low to {}, high to {}",
+            self.bot, self.low, self.high, self.low_target, self.high_target
+        )
+    }
+}
+
 struct BotSystem {
     bot_rules: HashMap<usize, BotRule>,
     bot_vals: HashMap<usize, BotVal>,
     outputs: HashMap<usize, usize>,
+    assignments: Vec<(usize, usize)>,
+    log: Vec<Exchange>,
 }
 
 impl BotSystem {
     fn from_commands(commands: &[Command]) -> Self {
         let mut bot_rules: HashMap<usize, BotRule> = HashMap::new();
         let mut bot_vals: HashMap<usize, BotVal> = HashMap::new();
+        let mut assignments = Vec::new();
         for command in commands {
             match *command {
                 Command::Assign { num, bot } => {
                     bot_vals.entry(bot).or_default().insert(num);
+                    assignments.push((num, bot));
                 }
                 Command::Bot { bot, rule } => {
                     let previous = bot_rules.insert(bot, rule);
@@ -158,6 +195,8 @@ impl BotSystem {
             bot_rules,
             bot_vals,
             outputs: Default::default(),
+            assignments,
+            log: Vec::new(),
         }
     }
 
@@ -173,6 +212,13 @@ impl BotSystem {
                 high_target,
             }) = self.bot_rules.get(&full_bot)
             {
+                self.log.push(Exchange {
+                    bot: full_bot,
+                    low,
+                    high,
+                    low_target,
+                    high_target,
+                });
                 for (target, val) in [(low_target, low), (high_target, high)] {
                     match target {
                         Target::Bot(target) => {
@@ -194,6 +240,276 @@ impl BotSystem {
         }
         assert!(full_bots.is_empty());
     }
+
+    // bot that compared the two values (in any order) while running
+    fn compared_by(&self, a: usize, b: usize) -> Option<usize> {
+        let (low, high) = (a.min(b), a.max(b));
+        self.log
+            .iter()
+            .find(|exchange| exchange.low == low && exchange.high == high)
+            .map(|exchange| exchange.bot)
+    }
+
+    // graphviz graph of the rules, edges are labelled with values that went through them
+    fn to_dot(&self) -> String {
+        let mut flows: HashMap<(usize, &str), Vec<usize>> = HashMap::new();
+        for exchange in &self.log {
+            flows
+                .entry((exchange.bot, "low"))
+                .or_default()
+                .push(exchange.low);
+            flows
+                .entry((exchange.bot, "high"))
+                .or_default()
+                .push(exchange.high);
+        }
+
+        let mut bots = self.bot_rules.keys().copied().collect::<Vec<_>>();
+        bots.sort_unstable();
+        let mut outputs = BTreeSet::new();
+        let mut dot = String::from("digraph bots {\n");
+        for (num, bot) in &self.assignments {
+            writeln!(dot, "    \"value {}\" [shape=plaintext];", num).unwrap();
+            writeln!(
+                dot,
+                "    \"value {}\" -> \"{}\" [label=\"{}\"];",
+                num,
+                Target::Bot(*bot),
+                num
+            )
+            .unwrap();
+        }
+        for bot in bots {
+            let rule = self.bot_rules[&bot];
+            for (kind, target) in [("low", rule.low_target), ("high", rule.high_target)] {
+                if let Target::Output(output) = target {
+                    outputs.insert(output);
+                }
+                let attrs = match flows.get(&(bot, kind)) {
+                    Some(values) => {
+                        let values = values.iter().map(usize::to_string).collect::<Vec<_>>();
+                        format!("label=\"{}: {}\"", kind, values.join(", "))
+                    }
+                    None => format!("label=\"{}\", style=dashed", kind),
+                };
+                writeln!(
+                    dot,
+                    "    \"{}\" -> \"{}\" [{}];",
+                    Target::Bot(bot),
+                    target,
+                    attrs
+                )
+                .unwrap();
+            }
+        }
+        for output in outputs {
+            writeln!(dot, "    \"{}\" [shape=box];", Target::Output(output)).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Diagnostic {
+    // more than one rule for the same bot
+    DuplicateRule {
+        bot: usize,
+        rules: Vec<BotRule>,
+    },
+    // bot received more than two chips
+    TooManyChips {
+        bot: usize,
+        values: Vec<usize>,
+    },
+    // bot got full but has no rule telling where to pass chips
+    MissingRule {
+        bot: usize,
+        low: usize,
+        high: usize,
+    },
+    // more than one chip ends up in the same output, `writes` is (bot, value)
+    OutputConflict {
+        output: usize,
+        writes: Vec<(usize, usize)>,
+    },
+    // bots passing chips around in a loop
+    Cycle {
+        bots: Vec<usize>,
+    },
+    // bot holding a single chip after everything else is done
+    StuckChip {
+        bot: usize,
+        value: usize,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |items: &mut dyn Iterator<Item = String>| items.collect::<Vec<_>>().join(", ");
+        match self {
+            Self::DuplicateRule { bot, rules } => write!(
+                f,
+                "bot {} has {} rules: {}",
+                bot,
+                rules.len(),
+                join(&mut rules.iter().map(|rule| format!(
+                    "low to {} and high to {}",
+                    rule.low_target, rule.high_target
+                )))
+            ),
+            Self::TooManyChips { bot, values } => write!(
+                f,
+                "bot {} receives {} chips: {}",
+                bot,
+                values.len(),
+                join(&mut values.iter().map(usize::to_string))
+            ),
+            Self::MissingRule { bot, low, high } => {
+                write!(f, "bot {} holds {} and {} but has no rule", bot, low, high)
+            }
+            Self::OutputConflict { output, writes } => write!(
+                f,
+                "output {} is written {} times: {}",
+                output,
+                writes.len(),
+                join(
+                    &mut writes
+                        .iter()
+                        .map(|(bot, value)| format!("{} from bot {}", value, bot))
+                )
+            ),
+            Self::Cycle { bots } => write!(
+                f,
+                "bots form a cycle: {}",
+                join(&mut bots.iter().map(|bot| format!("bot {}", bot)))
+            ),
+            Self::StuckChip { bot, value } => {
+                write!(f, "bot {} is stuck holding only {}", bot, value)
+            }
+        }
+    }
+}
+
+// finds everything that would make `BotSystem` panic or stop early
+fn validate(commands: &[Command]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut all_rules: HashMap<usize, Vec<BotRule>> = HashMap::new();
+    let mut chips: HashMap<usize, Vec<usize>> = HashMap::new();
+    for command in commands {
+        match *command {
+            Command::Assign { num, bot } => chips.entry(bot).or_default().push(num),
+            Command::Bot { bot, rule } => all_rules.entry(bot).or_default().push(rule),
+        }
+    }
+    let mut bots = all_rules.keys().copied().collect::<Vec<_>>();
+    bots.sort_unstable();
+    for &bot in &bots {
+        if all_rules[&bot].len() > 1 {
+            diagnostics.push(Diagnostic::DuplicateRule {
+                bot,
+                rules: all_rules[&bot].clone(),
+            });
+        }
+    }
+    // `BotSystem` rejects duplicate rules (reported above), the other checks go on with the
+    // first rule of each bot
+    let rules: HashMap<usize, BotRule> = all_rules
+        .iter()
+        .map(|(&bot, rules)| (bot, rules[0]))
+        .collect();
+
+    // cycles: depth first search reporting every back edge
+    fn visit(
+        bot: usize,
+        rules: &HashMap<usize, BotRule>,
+        path: &mut Vec<usize>,
+        done: &mut BTreeSet<usize>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        if done.contains(&bot) {
+            return;
+        }
+        if let Some(idx) = path.iter().position(|&on_path| on_path == bot) {
+            diagnostics.push(Diagnostic::Cycle {
+                bots: path[idx..].to_vec(),
+            });
+            return;
+        }
+        path.push(bot);
+        if let Some(rule) = rules.get(&bot) {
+            for target in [rule.low_target, rule.high_target] {
+                if let Target::Bot(target) = target {
+                    visit(target, rules, path, done, diagnostics);
+                }
+            }
+        }
+        path.pop();
+        done.insert(bot);
+    }
+    let mut done = BTreeSet::new();
+    for &bot in &bots {
+        visit(bot, &rules, &mut Vec::new(), &mut done, &mut diagnostics);
+    }
+
+    // simulate, every bot hands off at most once
+    let mut outputs: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+    let mut fired = BTreeSet::new();
+    let mut ready = chips
+        .iter()
+        .filter(|(_, values)| values.len() >= 2)
+        .map(|(&bot, _)| bot)
+        .collect::<Vec<_>>();
+    ready.sort_unstable_by(|a, b| b.cmp(a));
+    while let Some(bot) = ready.pop() {
+        if !fired.insert(bot) {
+            continue;
+        }
+        let (low, high) = {
+            let values = &chips[&bot];
+            (values[0].min(values[1]), values[0].max(values[1]))
+        };
+        let Some(rule) = rules.get(&bot) else {
+            diagnostics.push(Diagnostic::MissingRule { bot, low, high });
+            continue;
+        };
+        for (target, value) in [(rule.low_target, low), (rule.high_target, high)] {
+            match target {
+                Target::Bot(target) => {
+                    let values = chips.entry(target).or_default();
+                    values.push(value);
+                    if values.len() == 2 {
+                        ready.push(target);
+                    }
+                }
+                Target::Output(output) => outputs.entry(output).or_default().push((bot, value)),
+            }
+        }
+    }
+
+    let mut chip_bots = chips.keys().copied().collect::<Vec<_>>();
+    chip_bots.sort_unstable();
+    for bot in chip_bots {
+        match chips[&bot][..] {
+            [value] => diagnostics.push(Diagnostic::StuckChip { bot, value }),
+            [_, _] => {}
+            ref values => diagnostics.push(Diagnostic::TooManyChips {
+                bot,
+                values: values.to_vec(),
+            }),
+        }
+    }
+    let mut output_ids = outputs.keys().copied().collect::<Vec<_>>();
+    output_ids.sort_unstable();
+    for output in output_ids {
+        if outputs[&output].len() > 1 {
+            diagnostics.push(Diagnostic::OutputConflict {
+                output,
+                writes: outputs[&output].clone(),
+            });
+        }
+    }
+    diagnostics
 }
 
 impl Problem for Day10 {
@@ -204,17 +520,7 @@ impl Problem for Day10 {
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
         let mut system = BotSystem::from_commands(input);
         system.run();
-        system
-            .bot_vals
-            .into_iter()
-            .find_map(|(bot, val)| {
-                if let BotVal::Full(17, 61) = val {
-                    Some(bot)
-                } else {
-                    None
-                }
-            })
-            .expect("bot should be found")
+        system.compared_by(17, 61).expect("bot should be found")
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
@@ -226,7 +532,47 @@ impl Problem for Day10 {
 }
 
 fn main() {
-    solve::<Day10>(include_str!("../../inputs/day10.txt"));
+    let input = include_str!("../../inputs/day10.txt");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let input = match args.as_slice() {
+        [] => return solve::<Day10>(input),
+        ["check", path] => std::fs::read_to_string(path).expect("input file should be readable"),
+        _ => input.to_string(),
+    };
+    let commands = input
+        .lines()
+        .map(str::parse::<Command>)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("input should parse");
+    if let ["check", ..] = args.as_slice() {
+        let diagnostics = validate(&commands);
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+        if diagnostics.is_empty() {
+            println!("no problems found");
+        }
+        return;
+    }
+    let mut system = BotSystem::from_commands(&commands);
+    system.run();
+    match args.as_slice() {
+        ["log"] => {
+            for exchange in &system.log {
+                println!("{}", exchange);
+            }
+        }
+        ["dot"] => print!("{}", system.to_dot()),
+        ["compared", a, b] => {
+            let parse = |val: &str| val.parse::<usize>().expect("values should be numbers");
+            match system.compared_by(parse(a), parse(b)) {
+                Some(bot) => println!("{}", bot),
+                None => println!("no bot compared {} with {}", a, b),
+            }
+        }
+        _ => eprintln!("usage: day10 [log | dot | compared <value> <value> | check [path]]"),
+    }
 }
 
 #[cfg(test)]
@@ -260,4 +606,148 @@ mod tests {
             }
         );
     }
+
+    const SAMPLE: &str = "value 5 goes to bot 2
+bot 2 gives low to bot 1 and high to bot 0
+value 3 goes to bot 1
+bot 1 gives low to output 1 and high to bot 0
+bot 0 gives low to output 2 and high to output 0
+value 2 goes to bot 2";
+
+    fn sample_system() -> BotSystem {
+        let commands = SAMPLE
+            .lines()
+            .map(|line| line.parse::<Command>().unwrap())
+            .collect::<Vec<_>>();
+        let mut system = BotSystem::from_commands(&commands);
+        system.run();
+        system
+    }
+
+    #[test]
+    fn test_log() {
+        let system = sample_system();
+        assert_eq!(
+            system
+                .log
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "bot 2 compares 2 with 5: low to bot 1, high to bot 0",
+                "bot 1 compares 2 with 3: low to output 1, high to bot 0",
+                "bot 0 compares 3 with 5: low to output 2, high to output 0",
+            ]
+        );
+        assert_eq!(system.compared_by(5, 2), Some(2));
+        assert_eq!(system.compared_by(2, 3), Some(1));
+        assert_eq!(system.compared_by(3, 5), Some(0));
+        assert_eq!(system.compared_by(2, 2), None);
+        assert_eq!(system.compared_by(3, 7), None);
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = sample_system().to_dot();
+        assert!(dot.starts_with("digraph bots {\n"));
+        assert!(dot.contains("\"value 5\" -> \"bot 2\" [label=\"5\"];"));
+        assert!(dot.contains("\"bot 2\" -> \"bot 1\" [label=\"low: 2\"];"));
+        assert!(dot.contains("\"bot 1\" -> \"output 1\" [label=\"low: 2\"];"));
+        assert!(dot.contains("\"bot 0\" -> \"output 0\" [label=\"high: 5\"];"));
+        assert!(dot.contains("\"output 2\" [shape=box];"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    fn diagnostics(commands: &str) -> Vec<Diagnostic> {
+        let commands = commands
+            .lines()
+            .map(|line| line.trim().parse::<Command>().unwrap())
+            .collect::<Vec<_>>();
+        validate(&commands)
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(diagnostics(SAMPLE).is_empty());
+        assert!(diagnostics(include_str!("../../inputs/day10.txt")).is_empty());
+
+        assert_eq!(
+            diagnostics(
+                "value 1 goes to bot 0
+                value 2 goes to bot 0
+                value 3 goes to bot 0
+                bot 0 gives low to output 0 and high to output 1"
+            ),
+            [Diagnostic::TooManyChips {
+                bot: 0,
+                values: vec![1, 2, 3]
+            }]
+        );
+        assert_eq!(
+            diagnostics(
+                "value 1 goes to bot 0
+                value 2 goes to bot 0
+                bot 0 gives low to bot 1 and high to bot 1"
+            ),
+            [Diagnostic::MissingRule {
+                bot: 1,
+                low: 1,
+                high: 2
+            }]
+        );
+        assert_eq!(
+            diagnostics(
+                "value 1 goes to bot 0
+                value 2 goes to bot 0
+                bot 0 gives low to output 3 and high to output 3
+                bot 0 gives low to output 3 and high to output 4"
+            ),
+            [
+                Diagnostic::DuplicateRule {
+                    bot: 0,
+                    rules: vec![
+                        BotRule {
+                            low_target: Target::Output(3),
+                            high_target: Target::Output(3)
+                        },
+                        BotRule {
+                            low_target: Target::Output(3),
+                            high_target: Target::Output(4)
+                        },
+                    ]
+                },
+                Diagnostic::OutputConflict {
+                    output: 3,
+                    writes: vec![(0, 1), (0, 2)]
+                }
+            ]
+        );
+        assert_eq!(
+            diagnostics(
+                "value 1 goes to bot 0
+                value 5 goes to bot 0
+                value 3 goes to bot 2
+                bot 0 gives low to bot 1 and high to output 0
+                bot 1 gives low to bot 2 and high to output 1
+                bot 2 gives low to bot 0 and high to output 2"
+            ),
+            [
+                Diagnostic::Cycle {
+                    bots: vec![0, 1, 2]
+                },
+                Diagnostic::StuckChip { bot: 1, value: 1 },
+                Diagnostic::StuckChip { bot: 2, value: 3 },
+            ]
+        );
+        assert_eq!(
+            diagnostics(
+                "value 1 goes to bot 0
+                bot 0 gives low to output 0 and high to output 1"
+            )
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+            ["bot 0 is stuck holding only 1"]
+        );
+    }
 }
@@ -1,264 +1,681 @@
-use std::str::FromStr;
+use std::{
+    cell::Cell,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
 use aoc_helpers::{
     anyhow, bfs,
     scaffold::{solve, Problem, VecFromLines},
 };
+use lazy_static::lazy_static;
+use regex::Regex;
 
-const ELEMENTS: [&str; 7] = [
-    "promethium",
-    "cobalt",
-    "curium",
-    "ruthenium",
-    "plutonium",
-    "elerium",
-    "dilithium",
-];
-const EXTENDED_COUNT: usize = 2; // last 2 elements are extended
+// items the elevator can carry at once
+const ELEVATOR_CAPACITY: usize = 2;
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
-struct Floor {
-    generators: [bool; ELEMENTS.len()],
-    chips: [bool; ELEMENTS.len()],
-}
+// extra items found on the first floor in part 2
+const PART2_EXTRA: &str = "an elerium generator, an elerium-compatible microchip, \
+                           a dilithium generator and a dilithium-compatible microchip";
 
-impl Floor {
-    fn is_safe(&self) -> bool {
-        if !self.generators.iter().any(|b| *b) {
-            // no generators -> safe
-            return true;
-        }
-        for (chip, generator) in self.chips.iter().zip(self.generators.iter()) {
-            if *chip && !*generator {
-                // chip without its generator -> not safe
-                return false;
-            }
-        }
-        // all chips connected to their generators
-        true
-    }
+// items listed in a single floor description
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Floor {
+    generators: Vec<String>,
+    chips: Vec<String>,
 }
 
 impl FromStr for Floor {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut result: Self = Default::default();
-        for (idx, element) in ELEMENTS.into_iter().enumerate() {
-            result.generators[idx] = s.contains(&format!("a {element} generator"));
-            result.chips[idx] = s.contains(&format!("a {element}-compatible microchip"));
+        lazy_static! {
+            static ref GENERATOR_RE: Regex = Regex::new(r"\b([a-z]+) generator\b").unwrap();
+            static ref CHIP_RE: Regex = Regex::new(r"\b([a-z]+)-compatible microchip\b").unwrap();
         }
-        Ok(result)
+        let names = |re: &Regex| {
+            re.captures_iter(s)
+                .map(|caps| caps[1].to_string())
+                .collect::<Vec<_>>()
+        };
+        Ok(Self {
+            generators: names(&GENERATOR_RE),
+            chips: names(&CHIP_RE),
+        })
     }
 }
 
+// bit sets of elements, bit `i` stands for `Building::elements[i]`
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
-struct BuildingState {
-    floors: [Floor; 4],
-    elevator: usize,
+struct FloorItems {
+    generators: u64,
+    chips: u64,
 }
 
-impl BuildingState {
-    fn new(floors: &[Floor]) -> Self {
-        let floors = [floors[0], floors[1], floors[2], floors[3]];
+impl FloorItems {
+    fn is_safe(&self) -> bool {
+        // no generators or all chips connected to their generators
+        self.generators == 0 || self.chips & !self.generators == 0
+    }
+
+    fn is_empty(&self) -> bool {
+        self.generators == 0 && self.chips == 0
+    }
+
+    fn with(&self, other: &Self) -> Self {
         Self {
-            floors,
-            elevator: 0,
+            generators: self.generators | other.generators,
+            chips: self.chips | other.chips,
         }
     }
 
-    fn new_extended(floors: &[Floor]) -> Self {
-        let mut result = Self::new(floors);
-        for idx in (ELEMENTS.len() - EXTENDED_COUNT)..ELEMENTS.len() {
-            result.floors[0].chips[idx] = true;
-            result.floors[0].generators[idx] = true;
+    fn without(&self, other: &Self) -> Self {
+        Self {
+            generators: self.generators & !other.generators,
+            chips: self.chips & !other.chips,
         }
-        result
     }
 
-    fn is_final(&self) -> bool {
-        const SIMPLE_LEN: usize = ELEMENTS.len() - EXTENDED_COUNT;
-        const EMPTY: [bool; SIMPLE_LEN] = [false; SIMPLE_LEN];
-        const FULL: [bool; SIMPLE_LEN] = [true; SIMPLE_LEN];
-        self.floors[0].chips[..SIMPLE_LEN] == EMPTY
-            && self.floors[0].generators[..SIMPLE_LEN] == EMPTY
-            && self.floors[1].chips[..SIMPLE_LEN] == EMPTY
-            && self.floors[1].generators[..SIMPLE_LEN] == EMPTY
-            && self.floors[2].chips[..SIMPLE_LEN] == EMPTY
-            && self.floors[2].generators[..SIMPLE_LEN] == EMPTY
-            && self.floors[3].chips[..SIMPLE_LEN] == FULL
-            && self.floors[3].generators[..SIMPLE_LEN] == FULL
-    }
-
-    fn is_extended_final(&self) -> bool {
-        const EMPTY: [bool; ELEMENTS.len()] = [false; ELEMENTS.len()];
-        const FULL: [bool; ELEMENTS.len()] = [true; ELEMENTS.len()];
-        self.floors[0].chips == EMPTY
-            && self.floors[0].generators == EMPTY
-            && self.floors[1].chips == EMPTY
-            && self.floors[1].generators == EMPTY
-            && self.floors[2].chips == EMPTY
-            && self.floors[2].generators == EMPTY
-            && self.floors[3].chips == FULL
-            && self.floors[3].generators == FULL
-    }
-
-    fn try_move<F, G>(&self, target_floors: &[usize], update_fun: F, mut success_move_callback: G)
-    where
-        F: Fn(&mut Floor, bool),
-        G: FnMut(Self),
-    {
-        let mut new_state = *self;
-        update_fun(&mut new_state.floors[self.elevator], false);
-        if new_state.floors[self.elevator].is_safe() {
-            // taking the chip is safe
-            for target_floor in target_floors {
-                new_state.elevator = *target_floor;
-                update_fun(&mut new_state.floors[*target_floor], true);
-                if new_state.floors[*target_floor].is_safe() {
-                    // bringing the chip to target_floor is safe too (so the move is safe)
-                    success_move_callback(new_state);
+    // every load of 1 to `capacity` items from this floor
+    fn loads(&self, capacity: usize) -> Vec<Self> {
+        let bits = |mask: u64| (0..u64::BITS).filter(move |bit| mask & (1 << bit) != 0);
+        let items = bits(self.generators)
+            .map(|bit| Self {
+                generators: 1 << bit,
+                chips: 0,
+            })
+            .chain(bits(self.chips).map(|bit| Self {
+                generators: 0,
+                chips: 1 << bit,
+            }))
+            .collect::<Vec<_>>();
+        let mut loads = Vec::new();
+        // loads of the previous size and index of the first item that can still be added
+        let mut smaller = vec![(Self::default(), 0)];
+        for _ in 0..capacity {
+            let mut larger = Vec::new();
+            for (load, first) in smaller {
+                for (idx, item) in items.iter().enumerate().skip(first) {
+                    larger.push((load.with(item), idx + 1));
                 }
-                // take the chip back for the next iteration
-                update_fun(&mut new_state.floors[*target_floor], false);
             }
+            loads.extend(larger.iter().map(|(load, _)| *load));
+            smaller = larger;
         }
+        loads
     }
+}
 
-    fn possible_next_states(&self) -> Vec<Self> {
-        let mut states = Vec::new();
-
-        // possible target floors
-        let target_floors = if self.elevator == 0 {
-            vec![self.elevator + 1]
-        } else if self.elevator == self.floors.len() - 1 {
-            vec![self.elevator - 1]
-        } else {
-            vec![self.elevator + 1, self.elevator - 1]
-        };
+// puzzle setup: element names and the initial state
+#[derive(Clone, Debug)]
+struct Building {
+    elements: Vec<String>,
+    start: BuildingState,
+    capacity: usize,
+}
 
-        // move 1 chip and 1 gen
-        for (chip_idx, chip_present) in self.floors[self.elevator].chips.iter().enumerate() {
-            if *chip_present {
-                for (gen_idx, gen_present) in
-                    self.floors[self.elevator].generators.iter().enumerate()
-                {
-                    if *gen_present {
-                        self.try_move(
-                            &target_floors,
-                            |floor, val| {
-                                floor.chips[chip_idx] = val;
-                                floor.generators[gen_idx] = val;
-                            },
-                            |state| {
-                                states.push(state);
-                            },
-                        );
+impl Building {
+    // `extra` items are added to the first floor
+    fn new(floors: &[Floor], extra: &Floor) -> anyhow::Result<Self> {
+        if floors.is_empty() || floors.len() > u8::MAX as usize {
+            return Err(anyhow::anyhow!(
+                "Number of floors should be between 1 and {}",
+                u8::MAX
+            ));
+        }
+        let mut elements: Vec<String> = Vec::new();
+        let mut seen = FloorItems::default();
+        let mut items = vec![FloorItems::default(); floors.len()];
+        for (floor_idx, floor) in floors.iter().enumerate() {
+            let mut floor = floor.clone();
+            if floor_idx == 0 {
+                floor.generators.extend(extra.generators.iter().cloned());
+                floor.chips.extend(extra.chips.iter().cloned());
+            }
+            for (names, is_generator) in [(&floor.generators, true), (&floor.chips, false)] {
+                for name in names {
+                    let idx = match elements.iter().position(|element| element == name) {
+                        Some(idx) => idx,
+                        None => {
+                            if elements.len() == u64::BITS as usize {
+                                return Err(anyhow::anyhow!(
+                                    "Too many elements, at most {} are supported",
+                                    u64::BITS
+                                ));
+                            }
+                            elements.push(name.clone());
+                            elements.len() - 1
+                        }
+                    };
+                    let (seen_mask, floor_mask) = if is_generator {
+                        (&mut seen.generators, &mut items[floor_idx].generators)
+                    } else {
+                        (&mut seen.chips, &mut items[floor_idx].chips)
+                    };
+                    if *seen_mask & (1 << idx) != 0 {
+                        return Err(anyhow::anyhow!(
+                            "Duplicate {} {}",
+                            name,
+                            if is_generator {
+                                "generator"
+                            } else {
+                                "microchip"
+                            }
+                        ));
                     }
+                    *seen_mask |= 1 << idx;
+                    *floor_mask |= 1 << idx;
                 }
             }
         }
+        Ok(Self {
+            elements,
+            start: BuildingState {
+                floors: items,
+                elevator: 0,
+            },
+            capacity: ELEVATOR_CAPACITY,
+        })
+    }
+
+    // adds empty floors on top or removes empty ones from the top
+    fn with_floors(mut self, floors: usize) -> anyhow::Result<Self> {
+        if floors == 0 || floors > u8::MAX as usize {
+            return Err(anyhow::anyhow!(
+                "Number of floors should be between 1 and {}",
+                u8::MAX
+            ));
+        }
+        if let Some(idx) = self.start.floors[floors.min(self.start.floors.len())..]
+            .iter()
+            .position(|floor| !floor.is_empty())
+        {
+            return Err(anyhow::anyhow!(
+                "Floor {} isn't empty and can't be removed",
+                floors + idx + 1
+            ));
+        }
+        self.start.floors.resize(floors, FloorItems::default());
+        Ok(self)
+    }
 
-        // move 1 chip
-        for (chip_idx, chip_present) in self.floors[self.elevator].chips.iter().enumerate() {
-            if *chip_present {
-                self.try_move(
-                    &target_floors,
-                    |floor, val| floor.chips[chip_idx] = val,
-                    |state| {
-                        states.push(state);
-                    },
-                );
+    fn driver(&self) -> CanonicalDriver {
+        CanonicalDriver {
+            floors: self.start.floors.len(),
+            capacity: self.capacity,
+        }
+    }
+
+    fn min_steps(&self) -> Option<usize> {
+        bfs::find_lowest_cost(&self.driver(), 0, self.start.canonical(), None).final_cost
+    }
+
+    // optimal sequence of elevator trips
+    fn solution(&self) -> Option<Vec<Trip>> {
+        let driver = self.driver();
+        let result = bfs::find_lowest_cost(&driver, 0, self.start.canonical(), None);
+        let steps = result.final_cost?;
+
+        // walk back from the final state through states one step closer to the start
+        let mut by_cost = vec![Vec::new(); steps + 1];
+        for (state, cost) in result.seen_states {
+            if cost <= steps {
+                by_cost[cost].push(state);
             }
         }
+        let mut path = vec![by_cost[steps]
+            .iter()
+            .find(|state| bfs::FlatCostDriver::is_final(&driver, *state))
+            .expect("final state should be seen")
+            .clone()];
+        for cost in (0..steps).rev() {
+            let next = path.last().expect("path is never empty");
+            let previous = by_cost[cost]
+                .iter()
+                .find(|state| driver.next_states(state).contains(next))
+                .expect("a seen state should lead to the next one");
+            path.push(previous.clone());
+        }
+        path.reverse();
 
-        // move 2 chips
-        for (chip_idx, chip_present) in self.floors[self.elevator].chips.iter().enumerate() {
-            if *chip_present {
-                for (chip2_idx, chip2_present) in self.floors[self.elevator]
-                    .chips
+        // replay with the real elements
+        let mut state = self.start.clone();
+        let mut trips = Vec::new();
+        for next in &path[1..] {
+            let (load, to) = state
+                .moves(self.capacity)
+                .into_iter()
+                .find(|(load, to)| state.apply(load, *to).canonical() == *next)
+                .expect("some move should lead to the next state");
+            let from = state.elevator;
+            state = state.apply(&load, to);
+            trips.push(Trip {
+                load,
+                from,
+                to,
+                state: state.clone(),
+            });
+        }
+        Some(trips)
+    }
+
+    // short unique names like "HG" for hydrogen generator and "HM" for its microchip
+    fn abbreviations(&self) -> Vec<String> {
+        let max_len = self
+            .elements
+            .iter()
+            .map(String::len)
+            .max()
+            .unwrap_or_default();
+        let len = (1..=max_len)
+            .find(|len| {
+                let mut prefixes = self
+                    .elements
                     .iter()
-                    .enumerate()
-                    .skip(chip_idx)
-                {
-                    if *chip2_present {
-                        self.try_move(
-                            &target_floors,
-                            |floor, val| {
-                                floor.chips[chip_idx] = val;
-                                floor.chips[chip2_idx] = val;
-                            },
-                            |state| {
-                                states.push(state);
-                            },
-                        );
-                    }
+                    .map(|element| element.get(..*len).unwrap_or(element))
+                    .collect::<Vec<_>>();
+                prefixes.sort_unstable();
+                prefixes.dedup();
+                prefixes.len() == self.elements.len()
+            })
+            .unwrap_or(max_len);
+        self.elements
+            .iter()
+            .map(|element| {
+                let prefix = element.get(..len).unwrap_or(element);
+                let mut chars = prefix.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    fn describe_load(&self, load: &FloorItems) -> String {
+        let abbreviations = self.abbreviations();
+        let mut items = Vec::new();
+        for (idx, abbreviation) in abbreviations.iter().enumerate() {
+            if load.generators & (1 << idx) != 0 {
+                items.push(format!("{}G", abbreviation));
+            }
+            if load.chips & (1 << idx) != 0 {
+                items.push(format!("{}M", abbreviation));
+            }
+        }
+        items.join(", ")
+    }
+
+    // floors drawn top to bottom like in the puzzle description
+    fn render(&self, state: &BuildingState) -> String {
+        let abbreviations = self.abbreviations();
+        let mut lines = Vec::new();
+        for (floor_idx, floor) in state.floors.iter().enumerate().rev() {
+            let mut line = format!(
+                "F{} {} ",
+                floor_idx + 1,
+                if state.elevator == floor_idx {
+                    "E"
+                } else {
+                    "."
+                }
+            );
+            for (idx, abbreviation) in abbreviations.iter().enumerate() {
+                for (mask, kind) in [(floor.generators, 'G'), (floor.chips, 'M')] {
+                    let item = if mask & (1 << idx) != 0 {
+                        format!("{}{}", abbreviation, kind)
+                    } else {
+                        ".".to_string()
+                    };
+                    line.push_str(&format!(" {:width$}", item, width = abbreviation.len() + 1));
                 }
             }
+            lines.push(line.trim_end().to_string());
         }
+        lines.join("\n")
+    }
 
-        // move 1 gen
-        for (gen_idx, gen_present) in self.floors[self.elevator].generators.iter().enumerate() {
-            if *gen_present {
-                self.try_move(
-                    &target_floors,
-                    |floor, val| floor.generators[gen_idx] = val,
-                    |state| {
-                        states.push(state);
-                    },
-                );
+    // shortest path with A*, `heuristic` never overestimates the number of steps left
+    fn a_star(&self) -> (Option<usize>, usize, usize) {
+        let driver = self.driver();
+        let start = self.start.canonical();
+        let mut best = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        best.insert(start.clone(), 0);
+        queue.push(Reverse((driver.heuristic(&start), 0, start)));
+        let mut expanded = 0;
+        while let Some(Reverse((_, cost, state))) = queue.pop() {
+            if best[&state] < cost {
+                // already reached cheaper
+                continue;
+            }
+            if bfs::FlatCostDriver::is_final(&driver, &state) {
+                return (Some(cost), expanded, best.len());
+            }
+            expanded += 1;
+            for next in driver.next_states(&state) {
+                let next_cost = cost + 1;
+                if best.get(&next).is_some_and(|&known| known <= next_cost) {
+                    continue;
+                }
+                best.insert(next.clone(), next_cost);
+                queue.push(Reverse((
+                    next_cost + driver.heuristic(&next),
+                    next_cost,
+                    next,
+                )));
             }
         }
+        (None, expanded, best.len())
+    }
 
-        // move 2 gen
-        for (gen_idx, gen_present) in self.floors[self.elevator].generators.iter().enumerate() {
-            if *gen_present {
-                for (gen2_idx, gen2_present) in self.floors[self.elevator]
-                    .generators
-                    .iter()
-                    .enumerate()
-                    .skip(gen_idx)
-                {
-                    if *gen2_present {
-                        self.try_move(
-                            &target_floors,
-                            |floor, val| {
-                                floor.generators[gen_idx] = val;
-                                floor.generators[gen2_idx] = val;
-                            },
-                            |state| {
-                                states.push(state);
-                            },
-                        );
+    fn search_stats(&self, search: Search) -> SearchStats {
+        let started = Instant::now();
+        let (steps, expanded, seen) = match search {
+            Search::Plain => {
+                let driver = Counting::new(Driver {
+                    capacity: self.capacity,
+                });
+                let result = bfs::find_lowest_cost(&driver, 0, self.start.clone(), None);
+                (
+                    result.final_cost,
+                    driver.expanded.get(),
+                    result.seen_states.len(),
+                )
+            }
+            Search::Canonical => {
+                let driver = Counting::new(self.driver());
+                let result = bfs::find_lowest_cost(&driver, 0, self.start.canonical(), None);
+                (
+                    result.final_cost,
+                    driver.expanded.get(),
+                    result.seen_states.len(),
+                )
+            }
+            Search::AStar => self.a_star(),
+        };
+        SearchStats {
+            steps,
+            expanded,
+            seen,
+            elapsed: started.elapsed(),
+        }
+    }
+}
+
+// a single elevator ride, `state` is the building after it
+#[derive(Clone, Debug)]
+struct Trip {
+    load: FloorItems,
+    from: usize,
+    to: usize,
+    state: BuildingState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Search {
+    // breadth first over full states
+    Plain,
+    // breadth first over canonical states
+    Canonical,
+    // A* over canonical states
+    AStar,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SearchStats {
+    steps: Option<usize>,
+    // states whose transitions were generated
+    expanded: usize,
+    // states discovered
+    seen: usize,
+    elapsed: Duration,
+}
+
+// (generator floor, chip floor) of a single element
+type Pair = (Option<u8>, Option<u8>);
+
+// state with element names forgotten: states differing only by swapping elements are equal
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct CanonicalState {
+    // sorted
+    pairs: Vec<Pair>,
+    elevator: u8,
+}
+
+impl CanonicalState {
+    // any state with this canonical form, element `i` gets `pairs[i]`
+    fn to_state(&self, floors: usize) -> BuildingState {
+        let mut state = BuildingState {
+            floors: vec![FloorItems::default(); floors],
+            elevator: self.elevator as usize,
+        };
+        for (idx, (generator, chip)) in self.pairs.iter().enumerate() {
+            if let Some(floor) = generator {
+                state.floors[*floor as usize].generators |= 1 << idx;
+            }
+            if let Some(floor) = chip {
+                state.floors[*floor as usize].chips |= 1 << idx;
+            }
+        }
+        state
+    }
+
+    fn possible_next_states(&self, floors: usize, capacity: usize) -> Vec<Self> {
+        let mut states = self
+            .to_state(floors)
+            .moves(capacity)
+            .into_iter()
+            .map(|(load, target_floor)| {
+                let mut pairs = self.pairs.clone();
+                for (idx, (generator, chip)) in pairs.iter_mut().enumerate() {
+                    if load.generators & (1 << idx) != 0 {
+                        *generator = Some(target_floor as u8);
                     }
+                    if load.chips & (1 << idx) != 0 {
+                        *chip = Some(target_floor as u8);
+                    }
+                }
+                pairs.sort_unstable();
+                Self {
+                    pairs,
+                    elevator: target_floor as u8,
+                }
+            })
+            .collect::<Vec<_>>();
+        // moving either of two equal pairs leads to the same state
+        states.sort_unstable();
+        states.dedup();
+        states
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+struct BuildingState {
+    floors: Vec<FloorItems>,
+    elevator: usize,
+}
+
+impl BuildingState {
+    fn canonical(&self) -> CanonicalState {
+        let elements = self
+            .floors
+            .iter()
+            .map(|floor| u64::BITS - (floor.generators | floor.chips).leading_zeros())
+            .max()
+            .unwrap_or_default();
+        let mut pairs = vec![(None, None); elements as usize];
+        for (floor_idx, floor) in self.floors.iter().enumerate() {
+            for (idx, (generator, chip)) in pairs.iter_mut().enumerate() {
+                if floor.generators & (1 << idx) != 0 {
+                    *generator = Some(floor_idx as u8);
+                }
+                if floor.chips & (1 << idx) != 0 {
+                    *chip = Some(floor_idx as u8);
                 }
             }
         }
+        pairs.retain(|pair| *pair != (None, None));
+        pairs.sort_unstable();
+        CanonicalState {
+            pairs,
+            elevator: self.elevator as u8,
+        }
+    }
 
-        states
+    fn is_final(&self) -> bool {
+        // everything on the top floor
+        self.floors[..self.floors.len() - 1]
+            .iter()
+            .all(FloorItems::is_empty)
+    }
+
+    // safe elevator trips as (load, target floor)
+    fn moves(&self, capacity: usize) -> Vec<(FloorItems, usize)> {
+        let mut moves = Vec::new();
+
+        // possible target floors
+        let target_floors = [self.elevator.checked_sub(1), Some(self.elevator + 1)]
+            .into_iter()
+            .flatten()
+            .filter(|floor| *floor < self.floors.len())
+            .collect::<Vec<_>>();
+
+        let current = self.floors[self.elevator];
+        for load in current.loads(capacity) {
+            if !current.without(&load).is_safe() {
+                // taking the load is not safe
+                continue;
+            }
+            for &target_floor in &target_floors {
+                if self.floors[target_floor].with(&load).is_safe() {
+                    // bringing the load to target_floor is safe too (so the move is safe)
+                    moves.push((load, target_floor));
+                }
+            }
+        }
+
+        moves
+    }
+
+    fn apply(&self, load: &FloorItems, target_floor: usize) -> Self {
+        let mut new_state = self.clone();
+        new_state.floors[self.elevator] = self.floors[self.elevator].without(load);
+        new_state.floors[target_floor] = self.floors[target_floor].with(load);
+        new_state.elevator = target_floor;
+        new_state
+    }
+
+    fn possible_next_states(&self, capacity: usize) -> Vec<Self> {
+        self.moves(capacity)
+            .into_iter()
+            .map(|(load, target_floor)| self.apply(&load, target_floor))
+            .collect()
     }
 }
 
 struct Driver {
-    extended: bool,
+    capacity: usize,
 }
 
 impl bfs::FlatCostDriver<BuildingState> for Driver {
     type TransitionsIterator = std::vec::IntoIter<BuildingState>;
 
     fn iter_transitions(&self, from_state: &BuildingState) -> Self::TransitionsIterator {
-        from_state.possible_next_states().into_iter()
+        from_state.possible_next_states(self.capacity).into_iter()
     }
 
     fn is_final(&self, state: &BuildingState) -> bool {
-        if self.extended {
-            state.is_extended_final()
-        } else {
-            state.is_final()
+        state.is_final()
+    }
+}
+
+struct CanonicalDriver {
+    floors: usize,
+    capacity: usize,
+}
+
+impl CanonicalDriver {
+    fn next_states(&self, state: &CanonicalState) -> Vec<CanonicalState> {
+        state.possible_next_states(self.floors, self.capacity)
+    }
+
+    // lower bound on the steps left: every item below the top has to cross each floor boundary
+    // above it and every trip up except the last needs a trip down carrying at least one item
+    fn heuristic(&self, state: &CanonicalState) -> usize {
+        let mut counts = vec![0usize; self.floors];
+        for (generator, chip) in &state.pairs {
+            for floor in [generator, chip].into_iter().flatten() {
+                counts[*floor as usize] += 1;
+            }
+        }
+        let mut below = 0;
+        let mut steps = 0;
+        for (boundary, count) in counts.iter().enumerate().take(self.floors - 1) {
+            below += count;
+            if below == 0 {
+                continue;
+            }
+            let elevator_below = state.elevator as usize <= boundary;
+            // items taken across the boundary by a single round trip
+            let per_round = self.capacity.saturating_sub(1);
+            steps += match (elevator_below, per_round) {
+                (true, 0) => 2 * below - 1,
+                (false, 0) => 2 * below,
+                (true, _) => 2 * (below - 1).div_ceil(per_round).max(1) - 1,
+                (false, _) => 2 * below.div_ceil(per_round),
+            };
+        }
+        steps
+    }
+}
+
+impl bfs::FlatCostDriver<CanonicalState> for CanonicalDriver {
+    type TransitionsIterator = std::vec::IntoIter<CanonicalState>;
+
+    fn iter_transitions(&self, from_state: &CanonicalState) -> Self::TransitionsIterator {
+        self.next_states(from_state).into_iter()
+    }
+
+    fn is_final(&self, state: &CanonicalState) -> bool {
+        let top = self.floors as u8 - 1;
+        state
+            .pairs
+            .iter()
+            .all(|(generator, chip)| generator.unwrap_or(top) == top && chip.unwrap_or(top) == top)
+    }
+}
+
+// counts the states expanded by a search
+struct Counting<D> {
+    driver: D,
+    expanded: Cell<usize>,
+}
+
+impl<D> Counting<D> {
+    fn new(driver: D) -> Self {
+        Self {
+            driver,
+            expanded: Cell::new(0),
         }
     }
 }
 
+impl<S, D: bfs::FlatCostDriver<S>> bfs::FlatCostDriver<S> for Counting<D> {
+    type TransitionsIterator = D::TransitionsIterator;
+
+    fn iter_transitions(&self, from_state: &S) -> Self::TransitionsIterator {
+        self.expanded.set(self.expanded.get() + 1);
+        self.driver.iter_transitions(from_state)
+    }
+
+    fn is_final(&self, state: &S) -> bool {
+        self.driver.is_final(state)
+    }
+}
+
 struct Day11;
 
 impl Problem for Day11 {
@@ -267,18 +684,325 @@ impl Problem for Day11 {
     type Part2 = usize;
 
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
-        let building = BuildingState::new(input);
-        let result = bfs::find_lowest_cost(&Driver { extended: false }, 0, building, None);
-        result.final_cost.expect("there should be a solution")
+        let building = Building::new(input, &Floor::default()).expect("input should be valid");
+        building.min_steps().expect("there should be a solution")
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        let building = BuildingState::new_extended(input);
-        let result = bfs::find_lowest_cost(&Driver { extended: true }, 0, building, None);
-        result.final_cost.expect("there should be a solution")
+        let extra = PART2_EXTRA
+            .parse::<Floor>()
+            .expect("extra items should parse");
+        let building = Building::new(input, &extra).expect("input should be valid");
+        building.min_steps().expect("there should be a solution")
     }
 }
 
 fn main() {
-    solve::<Day11>(include_str!("../../inputs/day11.txt"));
+    let input = include_str!("../../inputs/day11.txt");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() {
+        return solve::<Day11>(input);
+    }
+    // day11 [path] [--extra=<items>] [--capacity=N] [--floors=N] [--bench | --moves | --astar]
+    let extra = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("--extra="))
+        .unwrap_or_default()
+        .parse::<Floor>()
+        .expect("extra items should parse");
+    let input = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(path) => std::fs::read_to_string(path).expect("input file should be readable"),
+        None => input.to_string(),
+    };
+    let floors = input
+        .lines()
+        .map(str::parse::<Floor>)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("input should parse");
+    let option = |name: &str| {
+        args.iter()
+            .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+            .map(|val| {
+                val.parse::<usize>()
+                    .expect("option value should be a number")
+            })
+    };
+    let building = Building::new(&floors, &extra).and_then(|building| match option("--floors") {
+        Some(floors) => building.with_floors(floors),
+        None => Ok(building),
+    });
+    let mut building = match building {
+        Ok(building) => building,
+        Err(err) => return eprintln!("{}", err),
+    };
+    if let Some(capacity) = option("--capacity") {
+        building.capacity = capacity;
+    }
+    println!("elements: {}", building.elements.join(", "));
+    if args.iter().any(|arg| arg == "--bench") {
+        for (name, search) in [
+            ("a*", Search::AStar),
+            ("canonical", Search::Canonical),
+            ("plain", Search::Plain),
+        ] {
+            let stats = building.search_stats(search);
+            println!(
+                "{:>9}: {:?} steps, {} expanded, {} seen, {:.3}s",
+                name,
+                stats.steps,
+                stats.expanded,
+                stats.seen,
+                stats.elapsed.as_secs_f64()
+            );
+        }
+        return;
+    }
+    if args.iter().any(|arg| arg == "--astar") {
+        let stats = building.search_stats(Search::AStar);
+        match stats.steps {
+            Some(steps) => println!("steps: {} ({} states expanded)", steps, stats.expanded),
+            None => println!("no solution ({} states expanded)", stats.expanded),
+        }
+        return;
+    }
+    if args.iter().any(|arg| arg == "--moves") {
+        let Some(trips) = building.solution() else {
+            return println!("no solution");
+        };
+        println!("{}", building.render(&building.start));
+        for (idx, trip) in trips.iter().enumerate() {
+            println!(
+                "\n{}. floor {} -> {} with {}",
+                idx + 1,
+                trip.from + 1,
+                trip.to + 1,
+                building.describe_load(&trip.load)
+            );
+            println!("{}", building.render(&trip.state));
+        }
+        return;
+    }
+    match building.min_steps() {
+        Some(steps) => println!("steps: {}", steps),
+        None => println!("no solution"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc_helpers::scaffold::solve_part1;
+
+    const SAMPLE: &str = "The first floor contains a hydrogen-compatible microchip and a lithium-compatible microchip.
+The second floor contains a hydrogen generator.
+The third floor contains a lithium generator.
+The fourth floor contains nothing relevant.";
+
+    fn floors(s: &str) -> Vec<Floor> {
+        s.lines().map(|line| line.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!(
+            "The first floor contains a thulium generator, a thulium-compatible microchip, \
+             a plutonium generator, and a strontium generator."
+                .parse::<Floor>()
+                .unwrap(),
+            Floor {
+                generators: vec![
+                    "thulium".to_string(),
+                    "plutonium".to_string(),
+                    "strontium".to_string()
+                ],
+                chips: vec!["thulium".to_string()],
+            }
+        );
+        let building = Building::new(&floors(SAMPLE), &Floor::default()).unwrap();
+        assert_eq!(building.elements, ["hydrogen", "lithium"]);
+        assert_eq!(
+            building.start.floors,
+            [
+                FloorItems {
+                    generators: 0,
+                    chips: 0b11
+                },
+                FloorItems {
+                    generators: 0b01,
+                    chips: 0
+                },
+                FloorItems {
+                    generators: 0b10,
+                    chips: 0
+                },
+                FloorItems::default(),
+            ]
+        );
+        assert!(Building::new(
+            &floors("a lithium generator\na lithium generator"),
+            &Floor::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_min_steps() {
+        assert_eq!(solve_part1::<Day11>(SAMPLE), 11);
+        // two floors with a new element on the bottom
+        let building = Building::new(
+            &floors("a unobtainium generator and a unobtainium-compatible microchip\nnothing"),
+            &Floor::default(),
+        )
+        .unwrap();
+        assert_eq!(building.min_steps(), Some(1));
+        // extra items make it longer
+        let extra = "a lithium generator and a lithium-compatible microchip"
+            .parse::<Floor>()
+            .unwrap();
+        let building = Building::new(
+            &floors("a hydrogen generator and a hydrogen-compatible microchip\nnothing\nnothing"),
+            &extra,
+        )
+        .unwrap();
+        assert_eq!(building.min_steps(), Some(10));
+    }
+
+    #[test]
+    fn test_canonical() {
+        let building = Building::new(&floors(SAMPLE), &Floor::default()).unwrap();
+        let canonical = building.start.canonical();
+        assert_eq!(
+            canonical,
+            CanonicalState {
+                pairs: vec![(Some(1), Some(0)), (Some(2), Some(0))],
+                elevator: 0
+            }
+        );
+        assert_eq!(canonical.to_state(4), building.start);
+        assert_eq!(canonical.to_state(4).canonical(), canonical);
+
+        // swapping the elements gives the same canonical state
+        let mut swapped = building.start.clone();
+        swapped.floors[1].generators = 0b10;
+        swapped.floors[2].generators = 0b01;
+        assert_ne!(swapped, building.start);
+        assert_eq!(swapped.canonical(), canonical);
+
+        // missing items are kept apart from present ones
+        let lonely =
+            Building::new(&floors("a boron generator\nnothing"), &Floor::default()).unwrap();
+        assert_eq!(lonely.start.canonical().pairs, [(Some(0), None)]);
+        assert_eq!(lonely.min_steps(), Some(1));
+    }
+
+    #[test]
+    fn test_canonical_search() {
+        let extra = "a boron generator and a boron-compatible microchip"
+            .parse::<Floor>()
+            .unwrap();
+        let building = Building::new(&floors(SAMPLE), &extra).unwrap();
+        let plain = building.search_stats(Search::Plain);
+        let canonical = building.search_stats(Search::Canonical);
+        assert_eq!(plain.steps, Some(21));
+        assert_eq!(canonical.steps, plain.steps);
+        assert!(canonical.seen * 3 < plain.seen);
+        assert!(canonical.expanded * 3 < plain.expanded);
+    }
+
+    #[test]
+    fn test_solution() {
+        let building = Building::new(&floors(SAMPLE), &Floor::default()).unwrap();
+        assert_eq!(
+            building.render(&building.start),
+            "F4 .  .  .  .  .
+F3 .  .  .  LG .
+F2 .  HG .  .  .
+F1 E  .  HM .  LM"
+        );
+        let trips = building.solution().unwrap();
+        assert_eq!(trips.len(), 11);
+        let mut state = building.start.clone();
+        for trip in &trips {
+            assert_eq!(trip.from, state.elevator);
+            assert!(state.moves(2).contains(&(trip.load, trip.to)));
+            state = state.apply(&trip.load, trip.to);
+            assert_eq!(state, trip.state);
+        }
+        assert!(state.is_final());
+        assert_eq!(building.describe_load(&trips[0].load), "HM");
+
+        let cobalt = Building::new(
+            &floors("a cobalt generator, a curium generator\na cobalt-compatible microchip"),
+            &Floor::default(),
+        )
+        .unwrap();
+        assert_eq!(cobalt.abbreviations(), ["Co", "Cu"]);
+        assert_eq!(
+            cobalt.render(&cobalt.start),
+            "F2 .  .   CoM .   .
+F1 E  CoG .   CuG ."
+        );
+    }
+
+    #[test]
+    fn test_capacity_and_floors() {
+        let building = Building::new(
+            &floors("a hydrogen generator and a hydrogen-compatible microchip\nnothing"),
+            &"a lithium generator and a lithium-compatible microchip"
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(building.min_steps(), Some(5));
+        let mut bigger = building.clone();
+        bigger.capacity = 4;
+        assert_eq!(bigger.min_steps(), Some(1));
+        bigger.capacity = 3;
+        assert_eq!(bigger.min_steps(), Some(3));
+        let mut single = building.clone();
+        single.capacity = 1;
+        assert_eq!(single.min_steps(), None);
+
+        let taller = building.clone().with_floors(3).unwrap();
+        assert_eq!(taller.min_steps(), Some(10));
+        assert_eq!(taller.clone().with_floors(1).unwrap().min_steps(), Some(0));
+        assert!(taller.with_floors(0).is_err());
+        let sample = Building::new(&floors(SAMPLE), &Floor::default()).unwrap();
+        assert!(sample.clone().with_floors(2).is_err());
+        assert_eq!(sample.with_floors(3).unwrap().min_steps(), Some(6));
+    }
+
+    #[test]
+    fn test_a_star() {
+        let mut rng = 11u32;
+        let mut next = |max: usize| {
+            rng = rng.wrapping_mul(1103515245).wrapping_add(12345);
+            (rng >> 16) as usize % max
+        };
+        let mut buildings = vec![Building::new(&floors(SAMPLE), &Floor::default()).unwrap()];
+        for _ in 0..20 {
+            let floor_count = 2 + next(3);
+            let mut descriptions = vec![String::new(); floor_count];
+            for element in ["boron", "carbon", "neon"].iter().take(1 + next(3)) {
+                let generator = format!("a {} generator ", element);
+                descriptions[next(floor_count)].push_str(&generator);
+                let chip = format!("a {}-compatible microchip ", element);
+                descriptions[next(floor_count)].push_str(&chip);
+            }
+            let mut building =
+                Building::new(&floors(&descriptions.join("\n")), &Floor::default()).unwrap();
+            building.capacity = 1 + next(3);
+            buildings.push(building);
+        }
+        for building in buildings {
+            let bfs = building.search_stats(Search::Canonical);
+            let a_star = building.search_stats(Search::AStar);
+            assert_eq!(a_star.steps, bfs.steps);
+            assert!(a_star.expanded <= bfs.expanded);
+            let driver = building.driver();
+            if let Some(steps) = bfs.steps {
+                assert!(driver.heuristic(&building.start.canonical()) <= steps);
+            }
+        }
+    }
 }
@@ -1,35 +1,127 @@
+use std::collections::HashMap;
+
 use aoc_helpers::{
-    bfs,
+    anyhow, bfs,
     scaffold::{solve, Problem, TrimAndParse},
 };
 
 struct Day13;
 
+type Point = (isize, isize);
+
+const START: Point = (1, 1);
+const TARGET: Point = (31, 39);
+const STEP_BUDGET: usize = 50;
+
 #[derive(Clone, Copy, Debug)]
 struct MazeDriver {
     magic: isize,
+    // search stops here, `None` explores everything within the limit
+    target: Option<Point>,
 }
 
 impl MazeDriver {
     const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
 
-    fn new(magic: isize) -> Self {
-        Self { magic }
+    fn new(magic: isize, target: Option<Point>) -> Self {
+        Self { magic, target }
     }
 
-    fn is_a_wall(&self, (x, y): (isize, isize)) -> bool {
+    fn is_a_wall(&self, (x, y): Point) -> bool {
         if x < 0 || y < 0 {
             true
         } else {
             (x * x + 3 * x + 2 * x * y + y + y * y + self.magic).count_ones() % 2 == 1
         }
     }
+
+    // points from `start` to the target (both included)
+    fn shortest_path(&self, start: Point) -> Option<Vec<Point>> {
+        if self.is_a_wall(start) {
+            return None;
+        }
+        let result = bfs::find_lowest_cost(self, 0, start, None);
+        let cost = result.final_cost?;
+        let mut point = self.target?;
+        let mut path = vec![point];
+        for cost in (0..cost).rev() {
+            point = Self::DIRECTIONS
+                .iter()
+                .map(|(dx, dy)| (point.0 + dx, point.1 + dy))
+                .find(|previous| result.seen_states.get(previous) == Some(&cost))
+                .expect("some neighbour should be one step closer");
+            path.push(point);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // every point reachable in at most `steps` steps with its distance, flooded one step at a
+    // time so the last points added are exactly `steps` away
+    fn reachable(&self, start: Point, steps: usize) -> HashMap<Point, usize> {
+        if self.is_a_wall(start) {
+            return HashMap::new();
+        }
+        let mut distances = HashMap::from([(start, 0)]);
+        let mut frontier = vec![start];
+        for step in 1..=steps {
+            let mut next = Vec::new();
+            for (x, y) in frontier {
+                for (dx, dy) in Self::DIRECTIONS {
+                    let point = (x + dx, y + dy);
+                    if !self.is_a_wall(point) && !distances.contains_key(&point) {
+                        distances.insert(point, step);
+                        next.push(point);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        distances
+    }
+
+    // office layout of `from..=to` with the path ('O') and the reachable area ('+') drawn over
+    // open spaces ('.'), start and target are 'S' and 'T'
+    fn render(
+        &self,
+        from: Point,
+        to: Point,
+        path: &[Point],
+        reachable: &HashMap<Point, usize>,
+    ) -> String {
+        let digit = |n: isize| char::from(b'0' + n.rem_euclid(10) as u8);
+        let mut lines = vec![format!(
+            "  {}",
+            (from.0..=to.0).map(digit).collect::<String>()
+        )];
+        for y in from.1..=to.1 {
+            let mut line = format!("{} ", digit(y));
+            for x in from.0..=to.0 {
+                let point = (x, y);
+                line.push(if path.first() == Some(&point) {
+                    'S'
+                } else if path.last() == Some(&point) {
+                    'T'
+                } else if path.contains(&point) {
+                    'O'
+                } else if reachable.contains_key(&point) {
+                    '+'
+                } else if self.is_a_wall(point) {
+                    '#'
+                } else {
+                    '.'
+                });
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
 }
 
-impl bfs::FlatCostDriver<(isize, isize)> for MazeDriver {
-    type TransitionsIterator = Box<dyn Iterator<Item = (isize, isize)>>;
+impl bfs::FlatCostDriver<Point> for MazeDriver {
+    type TransitionsIterator = Box<dyn Iterator<Item = Point>>;
 
-    fn iter_transitions(&self, (x, y): &(isize, isize)) -> Self::TransitionsIterator {
+    fn iter_transitions(&self, (x, y): &Point) -> Self::TransitionsIterator {
         let (x, y) = (*x, *y);
         let drv = *self;
         Box::new(
@@ -40,8 +132,8 @@ impl bfs::FlatCostDriver<(isize, isize)> for MazeDriver {
         )
     }
 
-    fn is_final(&self, state: &(isize, isize)) -> bool {
-        state == &(31, 39)
+    fn is_final(&self, state: &Point) -> bool {
+        self.target.as_ref() == Some(state)
     }
 }
 
@@ -51,23 +143,151 @@ impl Problem for Day13 {
     type Part2 = usize;
 
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
-        let driver = MazeDriver::new(*input);
-        bfs::find_lowest_cost(&driver, 0, (1, 1), None)
+        let driver = MazeDriver::new(*input, Some(TARGET));
+        bfs::find_lowest_cost(&driver, 0, START, None)
             .final_cost
             .expect("there should be a solution")
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        let driver = MazeDriver::new(*input);
-        let result = bfs::find_lowest_cost(&driver, 0, (1, 1), Some(53));
-        result
-            .seen_states
-            .into_iter()
-            .filter(|(_, cost)| *cost <= 50)
-            .count()
+        let driver = MazeDriver::new(*input, None);
+        driver.reachable(START, STEP_BUDGET).len()
     }
 }
 
+fn parse_point(s: &str) -> anyhow::Result<Point> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| anyhow::anyhow!("Expected x,y: {}", s))?;
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<isize>()
+            .map_err(|e| anyhow::anyhow!("Int parse error: {}", e))
+    };
+    Ok((parse(x)?, parse(y)?))
+}
+
 fn main() {
-    solve::<Day13>(include_str!("../../inputs/day13.txt"));
+    let input = include_str!("../../inputs/day13.txt");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() {
+        return solve::<Day13>(input);
+    }
+    // day13 [--magic=N] [--start=x,y] [--target=x,y] [--steps=N] [--size=WxH]
+    let option = |name: &str| {
+        args.iter()
+            .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+    };
+    let point = |name: &str, default: Point| {
+        option(name)
+            .map_or(Ok(default), parse_point)
+            .expect("point should parse")
+    };
+    let magic = option("--magic")
+        .unwrap_or(input.trim())
+        .parse::<isize>()
+        .expect("magic number should parse");
+    let start = point("--start", START);
+    let target = point("--target", TARGET);
+    let steps = option("--steps").map_or(STEP_BUDGET, |steps| {
+        steps.parse().expect("steps should be a number")
+    });
+
+    let driver = MazeDriver::new(magic, Some(target));
+    let path = driver.shortest_path(start).unwrap_or_default();
+    let reachable = driver.reachable(start, steps);
+    let (width, height) = match option("--size") {
+        Some(size) => {
+            let (width, height) = size.split_once('x').expect("size should be WxH");
+            (
+                width.parse().expect("width should be a number"),
+                height.parse().expect("height should be a number"),
+            )
+        }
+        None => {
+            let furthest = |coord: fn(&Point) -> isize| {
+                path.iter()
+                    .chain(reachable.keys())
+                    .chain([start, target].iter())
+                    .map(coord)
+                    .max()
+                    .unwrap_or_default()
+                    + 2
+            };
+            (furthest(|point| point.0), furthest(|point| point.1))
+        }
+    };
+    println!(
+        "{}",
+        driver.render((0, 0), (width - 1, height - 1), &path, &reachable)
+    );
+    match path.len() {
+        0 => println!("target can't be reached"),
+        len => println!("shortest path: {} steps", len - 1),
+    }
+    println!("reachable in {} steps: {}", steps, reachable.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let driver = MazeDriver::new(10, Some((7, 4)));
+        let path = driver.shortest_path((1, 1)).unwrap();
+        assert_eq!(path.len(), 12);
+        assert_eq!(driver.reachable((1, 1), 2).len(), 5);
+        assert_eq!(
+            driver.render((0, 0), (9, 6), &path, &HashMap::new()),
+            "  0123456789
+0 .#.####.##
+1 .S#..#...#
+2 #OOO.##...
+3 ###O#.###.
+4 .##OO#.T#.
+5 ..##OOOO#.
+6 #...##.###"
+        );
+        assert_eq!(
+            driver.render((0, 0), (4, 3), &[], &driver.reachable((1, 1), 2)),
+            "  01234
+0 +#.##
+1 ++#..
+2 #++..
+3 ###.#"
+        );
+        assert_eq!(
+            MazeDriver::new(10, Some((1, 0))).shortest_path((0, 1)),
+            None
+        );
+        assert_eq!(parse_point("3, 4").unwrap(), (3, 4));
+    }
+
+    #[test]
+    fn test_reachable() {
+        let driver = MazeDriver::new(10, None);
+        // flood fill one step at a time
+        let mut seen = HashMap::from([((1, 1), 0)]);
+        let mut frontier = vec![(1, 1)];
+        for steps in 0..=20 {
+            assert_eq!(driver.reachable((1, 1), steps), seen, "{} steps", steps);
+            let mut next = Vec::new();
+            for (x, y) in frontier {
+                for (dx, dy) in MazeDriver::DIRECTIONS {
+                    let point = (x + dx, y + dy);
+                    if !driver.is_a_wall(point) && !seen.contains_key(&point) {
+                        seen.insert(point, steps + 1);
+                        next.push(point);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        assert_eq!(driver.reachable((0, 0), 0).len(), 1);
+        let counts = (0..=6)
+            .map(|steps| driver.reachable((1, 1), steps).len())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![1, 3, 5, 6, 9, 11, 12]);
+    }
 }
@@ -1,88 +1,304 @@
-use std::collections::{HashMap, VecDeque};
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
-use aoc_helpers::scaffold::{solve, Problem};
+use aoc_helpers::{
+    anyhow,
+    scaffold::{solve, Problem},
+};
+use rayon::prelude::*;
 
 struct Day14;
 
-fn hash(rounds: usize, s: &str) -> String {
-    let mut result = s.to_owned();
-    for _ in 0..rounds {
-        result = format!("{:x}", md5::compute(result));
+const HASH_BATCH_SIZE: usize = 0x1000;
+
+type Digest = [u8; 16];
+const HEX_LEN: usize = 32;
+
+type Hex = [u8; HEX_LEN];
+
+fn write_hex(digest: &Digest, out: &mut Hex) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    for (idx, byte) in digest.iter().enumerate() {
+        out[2 * idx] = DIGITS[(byte >> 4) as usize];
+        out[2 * idx + 1] = DIGITS[(byte & 0xf) as usize];
     }
-    result
 }
 
-fn produce_hashes(rounds: usize, prefix: String) -> impl Iterator<Item = (usize, String)> {
-    (0..)
-        .into_iter()
-        .map(move |n| (n, hash(rounds, &format!("{prefix}{n}"))))
+// md5 applied `rounds` times (at least once), every round hashes the lowercase hex of the previous one
+fn stretched_digest(rounds: usize, input: &[u8]) -> Digest {
+    let mut digest = md5::compute(input).0;
+    let mut hex = [0; HEX_LEN];
+    for _ in 1..rounds {
+        write_hex(&digest, &mut hex);
+        digest = md5::compute(hex).0;
+    }
+    digest
+}
+
+fn hash(rounds: usize, s: &str) -> Hex {
+    let mut hex = [0; HEX_LEN];
+    write_hex(&stretched_digest(rounds, s.as_bytes()), &mut hex);
+    hex
+}
+
+fn default_cache_dir() -> PathBuf {
+    std::env::temp_dir().join("advent-of-code-2016-day14")
 }
 
-fn find_3_and_5(s: &str) -> (Option<u8>, Option<u8>) {
-    (
-        s.as_bytes().windows(3).find_map(|w| {
-            if w[0] == w[1] && w[1] == w[2] {
-                Some(w[0])
-            } else {
-                None
+// hashes of salt followed by 0, 1, 2, ... computed in parallel batches, optionally kept in a file
+struct HashStream {
+    salt: String,
+    rounds: usize,
+    digests: Vec<Digest>,
+    next: usize,
+    cache: Option<PathBuf>,
+}
+
+impl HashStream {
+    fn new(salt: &str, rounds: usize) -> Self {
+        Self {
+            salt: salt.to_owned(),
+            rounds,
+            digests: Vec::new(),
+            next: 0,
+            cache: None,
+        }
+    }
+
+    // loads digests saved in `dir` for the same salt and rounds and saves new ones there;
+    // the file starts with the salt and rounds, and a file that doesn't match them or whose
+    // first and last digest are wrong gets started over
+    fn with_cache(mut self, dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let salt_hex = self
+            .salt
+            .bytes()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>();
+        let path = dir.join(format!("{}-{}.md5", salt_hex, self.rounds));
+        let header = format!("{} {}\n", salt_hex, self.rounds);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        let digests = bytes
+            .strip_prefix(header.as_bytes())
+            .map(|saved| {
+                saved
+                    .chunks_exact(16)
+                    .map(|chunk| chunk.try_into().expect("chunks have 16 bytes"))
+                    .collect::<Vec<Digest>>()
+            })
+            .filter(|digests| {
+                let valid = |n: usize| digests[n] == self.digest_of(n);
+                digests.is_empty() || (valid(0) && valid(digests.len() - 1))
+            });
+        match digests {
+            Some(digests) => {
+                let whole = header.len() + 16 * digests.len();
+                if whole != bytes.len() {
+                    // drop a partially written digest
+                    fs::write(&path, &bytes[..whole])?;
+                }
+                self.digests = digests;
             }
-        }),
-        s.as_bytes().windows(5).find_map(|w| {
-            if w[0] == w[1] && w[1] == w[2] && w[2] == w[3] && w[3] == w[4] {
-                Some(w[0])
-            } else {
-                None
+            None => {
+                if !bytes.is_empty() {
+                    eprintln!("hash cache {} doesn't match, starting over", path.display());
+                }
+                fs::write(&path, header)?;
             }
-        }),
-    )
-}
+        }
+        self.cache = Some(path);
+        Ok(self)
+    }
 
-fn find_64th_n(hashing_rounds: usize, prefix: String) -> usize {
-    let mut next = {
-        let mut nums_iter =
-            produce_hashes(hashing_rounds, prefix).map(|(n, s)| (n, find_3_and_5(&s)));
-        move || nums_iter.next().expect("there should always be next hash")
-    };
+    fn digest_of(&self, n: usize) -> Digest {
+        stretched_digest(self.rounds, format!("{}{}", self.salt, n).as_bytes())
+    }
 
-    let mut queue = VecDeque::new();
-    let mut fives: HashMap<u8, usize> = HashMap::new();
+    fn compute_batch(&mut self) {
+        let start = self.digests.len();
+        let (salt, rounds) = (self.salt.as_bytes(), self.rounds);
+        let batch = (start..start + HASH_BATCH_SIZE)
+            .into_par_iter()
+            .map_init(Vec::new, |input, n| {
+                input.clear();
+                input.extend_from_slice(salt);
+                write!(input, "{}", n).expect("writing to a vec can't fail");
+                stretched_digest(rounds, input)
+            })
+            .collect::<Vec<_>>();
+        if let Some(path) = &self.cache {
+            let saved = fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(batch.concat().as_slice()));
+            if let Err(err) = saved {
+                eprintln!(
+                    "hash cache disabled, can't write {}: {}",
+                    path.display(),
+                    err
+                );
+                self.cache = None;
+            }
+        }
+        self.digests.extend(batch);
+    }
+}
+
+impl Iterator for HashStream {
+    type Item = (usize, Hex);
 
-    // add 1001 elements to the queue for processing
-    for _ in 0..=1000 {
-        let (n, (set3, set5)) = next();
-        if let Some(x) = set5 {
-            *fives.entry(x).or_default() += 1;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.digests.len() {
+            self.compute_batch();
         }
-        queue.push_back((n, set3, set5));
+        let mut hex = [0; HEX_LEN];
+        write_hex(&self.digests[self.next], &mut hex);
+        self.next += 1;
+        Some((self.next - 1, hex))
     }
+}
 
-    let mut nums_found = 0;
-    let mut last_num_found = 0;
-    while nums_found < 64 {
-        // remove one
-        let (n, set3, set5) = queue.pop_front().expect("shouldn't be empty");
+// what makes a hash a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct KeyRules {
+    // the first run of this many same characters is the key candidate
+    run: usize,
+    // a run this long of the same character confirms the candidate...
+    confirm_run: usize,
+    // ...if it's in one of that many following hashes
+    window: usize,
+    // how many keys to find
+    keys: usize,
+}
 
-        // discard all 5s provided by this number
-        if let Some(x) = set5 {
-            *fives.get_mut(&x).expect("should already be inserted") -= 1;
+impl Default for KeyRules {
+    fn default() -> Self {
+        Self {
+            run: 3,
+            confirm_run: 5,
+            window: 1000,
+            keys: 64,
         }
+    }
+}
 
-        // check if any of the 3s from the removed num is in the remaining 5s (from the next 1000s hashes)
-        if let Some(x) = set3 {
-            if fives.get(&x).map(|v| *v > 0).unwrap_or_default() {
-                nums_found += 1;
-                last_num_found = n;
+impl KeyRules {
+    // rules that can never be satisfied by hashes of `hash_len` characters would search forever
+    fn validate(&self, hash_len: usize) -> anyhow::Result<()> {
+        for (name, len) in [("run", self.run), ("confirming run", self.confirm_run)] {
+            if !(1..=hash_len).contains(&len) {
+                return Err(anyhow::anyhow!(
+                    "The {} length should be between 1 and {}, got {}",
+                    name,
+                    hash_len,
+                    len
+                ));
             }
         }
+        if self.window == 0 && self.keys > 0 {
+            return Err(anyhow::anyhow!(
+                "Keys can't be confirmed in an empty window"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Key {
+    index: usize,
+    confirmed_by: usize,
+}
 
-        // add next one
-        let (n, (set3, set5)) = next();
-        if let Some(x) = set5 {
-            *fives.entry(x).or_default() += 1;
+fn first_run(s: &[u8], len: usize) -> Option<u8> {
+    s.windows(len.max(1))
+        .find(|w| w.iter().all(|c| *c == w[0]))
+        .map(|w| w[0])
+}
+
+// every character with a run of at least `len`
+fn runs(s: &[u8], len: usize) -> Vec<u8> {
+    let mut chars = s
+        .windows(len.max(1))
+        .filter(|w| w.iter().all(|c| *c == w[0]))
+        .map(|w| w[0])
+        .collect::<Vec<_>>();
+    chars.sort_unstable();
+    chars.dedup();
+    chars
+}
+
+// keys in order of their index, stops after `rules.keys` or when hashes run out;
+// `hashes` are (index, hash) from any hash function, e.g. `HashStream` or a mapped range
+fn find_keys<H, I>(rules: &KeyRules, hashes: I) -> Vec<Key>
+where
+    H: AsRef<[u8]>,
+    I: IntoIterator<Item = (usize, H)>,
+{
+    let mut hashes = hashes
+        .into_iter()
+        .map(|(n, hash)| {
+            let hash = hash.as_ref();
+            (n, first_run(hash, rules.run), runs(hash, rules.confirm_run))
+        })
+        .fuse();
+    // current hash followed by the ones in its window
+    let mut queue = VecDeque::new();
+
+    let mut keys = Vec::new();
+    while keys.len() < rules.keys {
+        while queue.len() <= rules.window {
+            match hashes.next() {
+                Some(hash) => queue.push_back(hash),
+                None => break,
+            }
+        }
+        let Some((n, candidate, _)) = queue.pop_front() else {
+            break;
+        };
+        if let Some(c) = candidate {
+            let confirmation = queue
+                .iter()
+                .take_while(|(next, _, _)| *next <= n + rules.window)
+                .find(|(_, _, confirming)| confirming.contains(&c));
+            if let Some((confirmed_by, _, _)) = confirmation {
+                keys.push(Key {
+                    index: n,
+                    confirmed_by: *confirmed_by,
+                });
+            }
         }
-        queue.push_back((n, set3, set5));
     }
-    last_num_found
+    keys
+}
+
+fn last_key_index(hashes: HashStream) -> usize {
+    find_keys(&KeyRules::default(), hashes)
+        .last()
+        .expect("keys should be found")
+        .index
+}
+
+// hash stream using the cache in `cache` if it's given and can be used
+fn hash_stream(salt: &str, rounds: usize, cache: Option<&Path>) -> HashStream {
+    let Some(dir) = cache else {
+        return HashStream::new(salt, rounds);
+    };
+    HashStream::new(salt, rounds)
+        .with_cache(dir)
+        .unwrap_or_else(|err| {
+            eprintln!("hash cache not available: {}", err);
+            HashStream::new(salt, rounds)
+        })
 }
 
 impl Problem for Day14 {
@@ -91,16 +307,69 @@ impl Problem for Day14 {
     type Part2 = usize;
 
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
-        find_64th_n(1, input.to_owned())
+        last_key_index(HashStream::new(input, 1))
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        find_64th_n(2017, input.to_owned())
+        last_key_index(HashStream::new(input, 2017))
     }
 }
 
 fn main() {
-    solve::<Day14>(include_str!("../../inputs/day14.txt"));
+    let input = include_str!("../../inputs/day14.txt").trim();
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let option = |name: &str, default: usize| {
+        args.iter()
+            .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+            .map_or(default, |val| {
+                val.parse::<usize>()
+                    .expect("option value should be a number")
+            })
+    };
+    // --cache keeps the hashes in the temp dir, --cache=<dir> somewhere else
+    let cache = args.iter().find_map(|arg| match arg.as_str() {
+        "--cache" => Some(default_cache_dir()),
+        _ => arg.strip_prefix("--cache=").map(PathBuf::from),
+    });
+    let positional = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+    match positional.as_slice() {
+        ["hash", text, rounds] => {
+            let rounds = rounds.parse().expect("rounds should be a number");
+            let hex = hash(rounds, text);
+            println!("{}", std::str::from_utf8(&hex).expect("hex is ascii"));
+        }
+        // keys [salt] [--rounds=N] [--run=N] [--confirm=N] [--window=N] [--keys=N] [--cache[=dir]]
+        ["keys", rest @ ..] => {
+            let defaults = KeyRules::default();
+            let rules = KeyRules {
+                run: option("--run", defaults.run),
+                confirm_run: option("--confirm", defaults.confirm_run),
+                window: option("--window", defaults.window),
+                keys: option("--keys", defaults.keys),
+            };
+            if let Err(e) = rules.validate(HEX_LEN) {
+                return eprintln!("{e}");
+            }
+            let salt = rest.first().copied().unwrap_or(input);
+            let hashes = hash_stream(salt, option("--rounds", 1), cache.as_deref());
+            for key in find_keys(&rules, hashes) {
+                println!("{} confirmed by {}", key.index, key.confirmed_by);
+            }
+        }
+        // [--cache[=dir]]
+        _ => match cache {
+            Some(dir) => {
+                println!("Part 1: {}", last_key_index(HashStream::new(input, 1)));
+                let hashes = hash_stream(input, 2017, Some(&dir));
+                println!("Part 2: {}", last_key_index(hashes));
+            }
+            None => solve::<Day14>(input),
+        },
+    }
 }
 
 #[cfg(test)]
@@ -112,10 +381,129 @@ mod tests {
 
     #[test]
     fn test_hashing() {
-        assert_eq!(hash(1, "abc0"), "577571be4de9dcce85a041ba0410f29f");
-        assert_eq!(hash(2, "abc0"), "eec80a0c92dc8a0777c619d9bb51e910");
-        assert_eq!(hash(3, "abc0"), "16062ce768787384c81fe17a7a60c7e3");
-        assert_eq!(hash(2017, "abc0"), "a107ff634856bb300138cac6568c0f24");
+        assert_eq!(&hash(1, "abc0"), b"577571be4de9dcce85a041ba0410f29f");
+        assert_eq!(&hash(2, "abc0"), b"eec80a0c92dc8a0777c619d9bb51e910");
+        assert_eq!(&hash(3, "abc0"), b"16062ce768787384c81fe17a7a60c7e3");
+        assert_eq!(&hash(2017, "abc0"), b"a107ff634856bb300138cac6568c0f24");
+    }
+
+    #[test]
+    fn test_hash_stream() {
+        let dir = std::env::temp_dir().join(format!("day14-test-{}", std::process::id()));
+        let expected = (0..HASH_BATCH_SIZE + 10)
+            .map(|n| (n, hash(3, &format!("abc{}", n))))
+            .collect::<Vec<_>>();
+        let stream = HashStream::new("abc", 3).with_cache(&dir).unwrap();
+        assert_eq!(stream.take(expected.len()).collect::<Vec<_>>(), expected);
+
+        // second stream reads the saved batches
+        let cached = HashStream::new("abc", 3).with_cache(&dir).unwrap();
+        assert_eq!(cached.digests.len(), 2 * HASH_BATCH_SIZE);
+        assert_eq!(cached.take(expected.len()).collect::<Vec<_>>(), expected);
+        // other rounds don't share the file
+        assert!(HashStream::new("abc", 4)
+            .with_cache(&dir)
+            .unwrap()
+            .digests
+            .is_empty());
+
+        // a file for other salt or rounds, or with wrong digests, isn't used
+        let path = dir.join("616263-3.md5");
+        let saved = fs::read(&path).unwrap();
+        let mut corrupt = saved.clone();
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        fs::write(&path, &corrupt).unwrap();
+        assert!(HashStream::new("abc", 3)
+            .with_cache(&dir)
+            .unwrap()
+            .digests
+            .is_empty());
+        fs::write(&path, [b"616263 4\n", &saved[9..]].concat()).unwrap();
+        let restarted = HashStream::new("abc", 3).with_cache(&dir).unwrap();
+        assert!(restarted.digests.is_empty());
+        assert_eq!(fs::read(&path).unwrap(), b"616263 3\n");
+        assert_eq!(restarted.take(3).collect::<Vec<_>>(), expected[..3]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_keys() {
+        let keys = find_keys(&KeyRules::default(), HashStream::new("abc", 1));
+        assert_eq!(keys.len(), 64);
+        assert_eq!(
+            keys[..2],
+            [
+                Key {
+                    index: 39,
+                    confirmed_by: 816
+                },
+                Key {
+                    index: 92,
+                    confirmed_by: 200
+                }
+            ]
+        );
+        assert_eq!(keys[63].index, 22728);
+
+        // any function works as a hash
+        let rules = KeyRules {
+            run: 2,
+            confirm_run: 3,
+            window: 2,
+            keys: 10,
+        };
+        let hash_fn = |n: usize| match n % 4 {
+            0 => "abbc",
+            1 => "xyz",
+            2 => "bbbx",
+            _ => "ccc",
+        };
+        let hashes = (0..).map(|n| (n, hash_fn(n)));
+        assert_eq!(
+            find_keys(&rules, hashes),
+            (0..10)
+                .map(|k| Key {
+                    index: 4 * k,
+                    confirmed_by: 4 * k + 2
+                })
+                .collect::<Vec<_>>()
+        );
+        // running out of hashes stops the search
+        assert!(find_keys(&KeyRules::default(), [(0, b"aaaaa")]).is_empty());
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        let defaults = KeyRules::default();
+        assert!(defaults.validate(HEX_LEN).is_ok());
+        for rules in [
+            KeyRules { run: 0, ..defaults },
+            KeyRules {
+                run: HEX_LEN + 1,
+                ..defaults
+            },
+            KeyRules {
+                confirm_run: 0,
+                ..defaults
+            },
+            KeyRules {
+                confirm_run: HEX_LEN + 1,
+                ..defaults
+            },
+            KeyRules {
+                window: 0,
+                ..defaults
+            },
+        ] {
+            assert!(rules.validate(HEX_LEN).is_err(), "{:?}", rules);
+        }
+        let longest = KeyRules {
+            run: HEX_LEN,
+            confirm_run: HEX_LEN,
+            ..defaults
+        };
+        assert!(longest.validate(HEX_LEN).is_ok());
     }
 
     #[test]
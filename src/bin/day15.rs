@@ -8,6 +8,9 @@ use std::str::FromStr;
 
 struct Day15;
 
+// positions of the disc added in part 2, it starts at position 0
+const PART2_EXTRA_POSITIONS: usize = 11;
+
 #[derive(Clone, Copy, Debug)]
 struct Disk {
     id: usize,
@@ -16,9 +19,83 @@ struct Disk {
 }
 
 impl Disk {
+    // position when a capsule dropped at `time` reaches the disc
     fn position_at(&self, time: usize) -> usize {
         (self.id + self.position_at_0 + time) % self.positions
     }
+
+    // drop times that let the capsule through this disc
+    fn schedule(&self) -> Schedule {
+        let offset = (self.id + self.position_at_0) % self.positions;
+        Schedule {
+            first: (self.positions - offset) % self.positions,
+            period: self.positions,
+        }
+    }
+}
+
+// times `first + k * period` for every k >= 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Schedule {
+    first: usize,
+    period: usize,
+}
+
+impl Schedule {
+    // times in both schedules (chinese remainder theorem, periods don't have to be coprime)
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        // returns (g, x, y) such that a * x + b * y = g = gcd(a, b)
+        fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+            if b == 0 {
+                (a, 1, 0)
+            } else {
+                let (g, x, y) = extended_gcd(b, a % b);
+                (g, y, x - (a / b) * y)
+            }
+        }
+
+        let (first, period) = (self.first as i128, self.period as i128);
+        let (other_first, other_period) = (other.first as i128, other.period as i128);
+        let (g, inverse, _) = extended_gcd(period, other_period);
+        let diff = other_first - first;
+        if diff % g != 0 {
+            return None;
+        }
+        // first + period * k = other_first (mod other_period)
+        let modulus = other_period / g;
+        let k = (diff / g * inverse).rem_euclid(modulus);
+        let period = period * modulus;
+        let first = (first + self.period as i128 * k).rem_euclid(period);
+        Some(Self {
+            first: first as usize,
+            period: usize::try_from(period).expect("period should fit in usize"),
+        })
+    }
+}
+
+// all drop times that get the capsule through every disc, `None` if there aren't any
+fn solve_disks(disks: &[Disk]) -> Option<Schedule> {
+    let schedule = disks.iter().try_fold(
+        Schedule {
+            first: 0,
+            period: 1,
+        },
+        |schedule, disk| schedule.intersect(&disk.schedule()),
+    )?;
+    debug_assert!(disks
+        .iter()
+        .all(|disk| disk.position_at(schedule.first) == 0));
+    Some(schedule)
+}
+
+fn with_extra_disk(disks: &[Disk], positions: usize, position_at_0: usize) -> Vec<Disk> {
+    let mut disks = disks.to_vec();
+    disks.push(Disk {
+        id: disks.iter().map(|disk| disk.id).max().unwrap_or_default() + 1,
+        positions,
+        position_at_0,
+    });
+    disks
 }
 
 impl FromStr for Disk {
@@ -43,6 +120,9 @@ impl FromStr for Disk {
             let id = parse_int(caps, 1)?;
             let positions = parse_int(caps, 2)?;
             let position_at_0 = parse_int(caps, 3)?;
+            if positions == 0 {
+                return Err(anyhow::anyhow!("Disc without positions: {}", s));
+            }
             Ok(Self {
                 id,
                 positions,
@@ -60,33 +140,53 @@ impl Problem for Day15 {
     type Part2 = usize;
 
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
-        // brute force
-        let (first, rest) = input.split_first().expect("there should some disks");
-        for time in 0.. {
-            let expected_position = first.position_at(time);
-            if rest
-                .iter()
-                .all(|disk| disk.position_at(time) == expected_position)
-            {
-                return time;
-            }
-        }
-        unreachable!("time should have been found");
+        solve_disks(input)
+            .expect("there should be a solution")
+            .first
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        let mut disks = input.to_vec();
-        disks.push(Disk {
-            id: disks.len() + 1,
-            positions: 11,
-            position_at_0: 0,
-        });
+        let disks = with_extra_disk(input, PART2_EXTRA_POSITIONS, 0);
         Self::solve_part1(&disks)
     }
 }
 
 fn main() {
-    solve::<Day15>(include_str!("../../inputs/day15.txt"));
+    let input = include_str!("../../inputs/day15.txt");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() {
+        return solve::<Day15>(input);
+    }
+    // day15 [--extra=POSITIONS] [--extra-start=POSITION]
+    let option = |name: &str| {
+        args.iter()
+            .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+            .map(|val| {
+                val.parse::<usize>()
+                    .expect("option value should be a number")
+            })
+    };
+    let disks = input
+        .lines()
+        .map(str::parse::<Disk>)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .expect("input should parse");
+    let disks = match option("--extra") {
+        Some(0) => return eprintln!("extra disc needs at least one position"),
+        Some(positions) => with_extra_disk(
+            &disks,
+            positions,
+            option("--extra-start").unwrap_or_default(),
+        ),
+        None => disks,
+    };
+    match solve_disks(&disks) {
+        Some(schedule) => println!(
+            "first time: {}, repeats every {}",
+            schedule.first, schedule.period
+        ),
+        None => println!("the capsule never gets through"),
+    }
 }
 
 #[cfg(test)]
@@ -100,4 +200,78 @@ mod tests {
     fn test_sample() {
         assert_eq!(solve_part1::<Day15>(SAMPLE), 5);
     }
+
+    fn disk(id: usize, positions: usize, position_at_0: usize) -> Disk {
+        Disk {
+            id,
+            positions,
+            position_at_0,
+        }
+    }
+
+    #[test]
+    fn test_solve_disks() {
+        assert_eq!(
+            solve_disks(&[disk(1, 5, 4), disk(2, 2, 1)]),
+            Some(Schedule {
+                first: 5,
+                period: 10
+            })
+        );
+        assert_eq!(
+            solve_disks(&[]),
+            Some(Schedule {
+                first: 0,
+                period: 1
+            })
+        );
+        // non-coprime positions
+        assert_eq!(
+            solve_disks(&[disk(1, 4, 0), disk(2, 6, 1)]),
+            Some(Schedule {
+                first: 3,
+                period: 12
+            })
+        );
+        // both discs need an even time but with different parity of the rest
+        assert_eq!(solve_disks(&[disk(1, 4, 0), disk(2, 6, 0)]), None);
+
+        // same as checking every time
+        let mut rng = 15u32;
+        let mut next = |max: usize| {
+            rng = rng.wrapping_mul(1103515245).wrapping_add(12345);
+            (rng >> 16) as usize % max
+        };
+        for _ in 0..200 {
+            let disks = (1..=1 + next(4))
+                .map(|id| {
+                    let positions = 1 + next(12);
+                    disk(id, positions, next(positions))
+                })
+                .collect::<Vec<_>>();
+            let period = disks.iter().map(|disk| disk.positions).product::<usize>();
+            let times = (0..2 * period)
+                .filter(|time| disks.iter().all(|disk| disk.position_at(*time) == 0))
+                .collect::<Vec<_>>();
+            match solve_disks(&disks) {
+                Some(schedule) => {
+                    let expected = (0..)
+                        .map(|k| schedule.first + k * schedule.period)
+                        .take_while(|time| *time < 2 * period)
+                        .collect::<Vec<_>>();
+                    assert_eq!(times, expected, "{:?}", disks);
+                }
+                None => assert!(times.is_empty(), "{:?}", disks),
+            }
+        }
+    }
+
+    #[test]
+    fn test_extra_disk() {
+        let disks = with_extra_disk(&[disk(1, 5, 4), disk(2, 2, 1)], 11, 0);
+        assert_eq!(disks.len(), 3);
+        assert_eq!(disks[2].id, 3);
+        assert_eq!(disks[2].positions, 11);
+        assert_eq!(solve_disks(&disks).unwrap().first, 85);
+    }
 }
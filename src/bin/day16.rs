@@ -2,106 +2,99 @@ use aoc_helpers::scaffold::{solve, Problem};
 
 struct Day16;
 
-#[derive(Debug)]
-enum BitSeq {
-    Simple(Vec<bool>),
-    Dragon(Box<BitSeq>),
-    Trimmed(Box<BitSeq>, usize),
-    Checksum(Box<BitSeq>),
+const WORD_BITS: usize = u64::BITS as usize;
+
+// dragon curve data grown from a seed `a`: a 0 b 0 a 1 b 0 a 0 b 1 a 1 b ... where b is the
+// reversed and flipped `a` and the joiners between blocks are the regular paperfolding sequence
+#[derive(Clone, Debug)]
+struct Dragon {
+    // seed bits packed into words, bit `i` is `words[i / WORD_BITS] >> (i % WORD_BITS) & 1`
+    words: Vec<u64>,
+    len: usize,
+    // parity of all words before the index
+    word_parities: Vec<bool>,
 }
 
-impl BitSeq {
-    fn new(bits: Vec<bool>) -> Self {
-        Self::Simple(bits)
-    }
-
-    fn dragon(self) -> Self {
-        Self::Dragon(Box::new(self))
-    }
-
-    fn trim(self, len: usize) -> Self {
-        if self.len() == len {
-            self
-        } else {
-            Self::Trimmed(Box::new(self), len)
+impl Dragon {
+    fn new(bits: &[bool]) -> Self {
+        let mut words = vec![0u64; bits.len().div_ceil(WORD_BITS)];
+        for (idx, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+            words[idx / WORD_BITS] |= 1 << (idx % WORD_BITS);
         }
-    }
-
-    fn checksum(self) -> Result<Self, Self> {
-        if self.len() % 2 == 0 {
-            Ok(Self::Checksum(Box::new(self)))
-        } else {
-            Err(self)
+        let word_parities = words
+            .iter()
+            .scan(false, |parity, word| {
+                let before = *parity;
+                *parity ^= word.count_ones() & 1 == 1;
+                Some(before)
+            })
+            .collect();
+        Self {
+            words,
+            len: bits.len(),
+            word_parities,
         }
     }
 
-    fn len(&self) -> usize {
-        match self {
-            BitSeq::Simple(v) => v.len(),
-            BitSeq::Dragon(b) => b.len() * 2 + 1,
-            BitSeq::Trimmed(_, l) => *l,
-            BitSeq::Checksum(b) => {
-                let l = b.len();
-                debug_assert!(l % 2 == 0);
-                l / 2
-            }
+    // parity of the first `count` bits of the seed
+    fn seed_parity(&self, count: usize) -> bool {
+        let (word, bits) = (count / WORD_BITS, count % WORD_BITS);
+        if bits == 0 {
+            self.word_parities.get(word).copied().unwrap_or_else(|| {
+                // count is the whole seed and ends at a word boundary
+                self.words.iter().map(|w| w.count_ones()).sum::<u32>() & 1 == 1
+            })
+        } else {
+            let partial = self.words[word] & ((1 << bits) - 1);
+            self.word_parities[word] ^ (partial.count_ones() & 1 == 1)
         }
     }
 
-    fn get(&self, idx: usize) -> bool {
-        match self {
-            BitSeq::Simple(v) => v[idx],
-            BitSeq::Dragon(b) => {
-                let len = b.len();
-                match idx.cmp(&len) {
-                    std::cmp::Ordering::Less => b.get(idx),
-                    std::cmp::Ordering::Equal => false,
-                    std::cmp::Ordering::Greater => {
-                        let relative_idx = len - 1 - (idx - len - 1);
-                        !b.get(relative_idx)
-                    }
-                }
-            }
-            BitSeq::Trimmed(b, l) => {
-                assert!(idx < *l);
-                b.get(idx)
-            }
-            BitSeq::Checksum(b) => b.get(idx * 2) == b.get(idx * 2 + 1),
+    // parity of the first `count` joiners, a joiner is 1 when the odd part of its (1 based)
+    // index is 3 mod 4
+    fn joiners_parity(count: usize) -> bool {
+        let mut ones = 0;
+        let mut count = count;
+        while count > 0 {
+            ones += (count + 1) / 4;
+            count /= 2;
         }
+        ones & 1 == 1
     }
-}
-
-struct BitSeqIterator {
-    seq: BitSeq,
-    idx: usize,
-    len: usize,
-}
-
-impl Iterator for BitSeqIterator {
-    type Item = bool;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let idx = self.idx;
-        if idx < self.len {
-            self.idx += 1;
-            Some(self.seq.get(idx))
-        } else {
-            None
+    // parity of the first `count` bits of the data
+    fn parity(&self, count: usize) -> bool {
+        let n = self.len;
+        // every block is followed by a joiner
+        let (blocks, rest) = (count / (n + 1), count % (n + 1));
+        let a = self.seed_parity(n);
+        let b = a ^ (n & 1 == 1);
+        let mut parity = (a && blocks.div_ceil(2) & 1 == 1)
+            ^ (b && (blocks / 2) & 1 == 1)
+            ^ Self::joiners_parity(blocks);
+        if rest > 0 {
+            parity ^= if blocks & 1 == 0 {
+                self.seed_parity(rest)
+            } else {
+                // the first `rest` bits of b are the last `rest` bits of a flipped
+                a ^ self.seed_parity(n - rest) ^ (rest & 1 == 1)
+            };
         }
+        parity
     }
-}
-
-impl IntoIterator for BitSeq {
-    type Item = bool;
 
-    type IntoIter = BitSeqIterator;
-
-    fn into_iter(self) -> Self::IntoIter {
-        Self::IntoIter {
-            len: self.len(),
-            seq: self,
-            idx: 0,
-        }
+    // checksum of the first `size` bits: every checksum round halves the data, so each final bit
+    // covers a chunk of the largest power of two dividing `size` and is 1 when that chunk has an
+    // even number of ones
+    fn checksum(&self, size: usize) -> impl Iterator<Item = bool> + '_ {
+        let rounds = if size == 0 { 0 } else { size.trailing_zeros() };
+        let chunk = 1 << rounds;
+        (0..size >> rounds).scan(false, move |before, idx| {
+            let after = self.parity((idx + 1) * chunk);
+            let bit = *before ^ after ^ (rounds > 0);
+            *before = after;
+            Some(bit)
+        })
     }
 }
 
@@ -109,15 +102,10 @@ const DISK_SIZE: usize = 272;
 const BIG_DISK_SIZE: usize = 35651584;
 
 fn fill_checksum(bits: Vec<bool>, size: usize) -> String {
-    let mut seq = BitSeq::new(bits);
-    while seq.len() < size {
-        seq = seq.dragon();
-    }
-    seq = seq.trim(size);
-    while seq.len() % 2 == 0 {
-        seq = seq.checksum().expect("should be even length");
-    }
-    seq.into_iter().map(|b| if b { '1' } else { '0' }).collect()
+    Dragon::new(&bits)
+        .checksum(size)
+        .map(|b| if b { '1' } else { '0' })
+        .collect()
 }
 
 impl Problem for Day16 {
@@ -137,7 +125,18 @@ impl Problem for Day16 {
 }
 
 fn main() {
-    solve::<Day16>(include_str!("../../inputs/day16.txt"));
+    let input = include_str!("../../inputs/day16.txt");
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.as_slice() {
+        // day16 <size> [seed]
+        [size, rest @ ..] => {
+            let size = size.parse::<usize>().expect("size should be a number");
+            let seed = rest.first().map_or(input.trim(), String::as_str);
+            let bits = seed.chars().map(|c| c == '1').collect::<Vec<_>>();
+            println!("{}", fill_checksum(bits, size));
+        }
+        [] => solve::<Day16>(input),
+    }
 }
 
 #[cfg(test)]
@@ -151,4 +150,65 @@ mod tests {
             "01100"
         );
     }
+
+    // dragon data built bit by bit like in the puzzle description
+    fn naive_checksum(bits: &[bool], size: usize) -> String {
+        let mut data = bits.to_vec();
+        while data.len() < size {
+            let b = data.iter().rev().map(|bit| !bit).collect::<Vec<_>>();
+            data.push(false);
+            data.extend(b);
+        }
+        data.truncate(size);
+        while !data.is_empty() && data.len() & 1 == 0 {
+            data = data.chunks(2).map(|pair| pair[0] == pair[1]).collect();
+        }
+        data.into_iter()
+            .map(|b| if b { '1' } else { '0' })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_as_naive() {
+        let mut rng = 16u32;
+        let mut next = |max: usize| {
+            rng = rng.wrapping_mul(1103515245).wrapping_add(12345);
+            (rng >> 16) as usize % max
+        };
+        for _ in 0..300 {
+            let bits = (0..next(150)).map(|_| next(2) == 1).collect::<Vec<_>>();
+            let size = next(3000);
+            assert_eq!(
+                fill_checksum(bits.clone(), size),
+                naive_checksum(&bits, size),
+                "{:?} {}",
+                bits,
+                size
+            );
+        }
+        let bits = "00101000101111010"
+            .chars()
+            .map(|c| c == '1')
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fill_checksum(bits.clone(), DISK_SIZE),
+            naive_checksum(&bits, DISK_SIZE)
+        );
+    }
+
+    #[test]
+    fn test_huge_disk() {
+        // way more than fits in memory, the checksum is only 17 bits long
+        let checksum = fill_checksum(vec![true, false, false, false, false], 17 << 50);
+        assert_eq!(checksum.len(), 17);
+        // long checksum chunks the naive builder still manages
+        let bits = "00101000101111010"
+            .chars()
+            .map(|c| c == '1')
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fill_checksum(bits.clone(), 17 << 20),
+            naive_checksum(&bits, 17 << 20)
+        );
+    }
 }
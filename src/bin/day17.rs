@@ -1,10 +1,217 @@
+use std::sync::Mutex;
+
 use aoc_helpers::{
+    anyhow,
     scaffold::{solve, Problem},
     walk,
 };
+use rayon::prelude::*;
 
 struct Day17;
 
+type Point = (isize, isize);
+
+const VAULT_SIZE: Point = (4, 4);
+const START: Point = (0, 0);
+const EXIT: Point = (3, 3);
+// lowest hash nibble (b) that opens a door
+const OPEN_FROM: u8 = 0x0b;
+// paths shorter than this split into parallel tasks
+const PARALLEL_DEPTH: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Vault {
+    passcode: String,
+    width: isize,
+    height: isize,
+    start: Point,
+    exit: Point,
+    open_from: u8,
+}
+
+impl Vault {
+    fn new(passcode: &str) -> Self {
+        Self {
+            passcode: passcode.to_owned(),
+            width: VAULT_SIZE.0,
+            height: VAULT_SIZE.1,
+            start: START,
+            exit: EXIT,
+            open_from: OPEN_FROM,
+        }
+    }
+
+    // checks the vault can be searched, `max_len` has to be given when doors open more often
+    // or there are more cells than in the puzzle: with more than one open door per step on
+    // average paths practically never run out
+    fn validate(&self, max_len: Option<usize>) -> anyhow::Result<()> {
+        if self.width <= 0 || self.height <= 0 {
+            return Err(anyhow::anyhow!(
+                "Vault should be at least 1x1, got {}x{}",
+                self.width,
+                self.height
+            ));
+        }
+        for (name, point) in [("Start", self.start), ("Exit", self.exit)] {
+            if !self.contains(point) {
+                return Err(anyhow::anyhow!(
+                    "{} {:?} is outside the {}x{} vault",
+                    name,
+                    point,
+                    self.width,
+                    self.height
+                ));
+            }
+        }
+        if self.open_from > 0x0f {
+            return Err(anyhow::anyhow!(
+                "Open door threshold should be a hex digit, got {:x}",
+                self.open_from
+            ));
+        }
+        let bigger = self.width > VAULT_SIZE.0 || self.height > VAULT_SIZE.1;
+        if (self.open_from < OPEN_FROM || bigger) && max_len.is_none() {
+            return Err(anyhow::anyhow!(
+                "A {}x{} vault with doors opening from {:x} allows endless paths, a max length is needed",
+                self.width,
+                self.height,
+                self.open_from
+            ));
+        }
+        Ok(())
+    }
+
+    fn contains(&self, (x, y): Point) -> bool {
+        (0..self.width).contains(&x) && (0..self.height).contains(&y)
+    }
+
+    fn start_state(&self) -> State {
+        State {
+            x: self.start.0,
+            y: self.start.1,
+            path: String::new(),
+        }
+    }
+
+    fn is_final(&self, state: &State) -> bool {
+        (state.x, state.y) == self.exit
+    }
+
+    // moves through the doors that the hash of the passcode and path so far opens
+    fn open_doors(&self, digest: &[u8; 16], (x, y): Point) -> std::vec::IntoIter<(Point, char)> {
+        let nibbles = [
+            digest[0] >> 4,
+            digest[0] & 0x0f,
+            digest[1] >> 4,
+            digest[1] & 0x0f,
+        ];
+        nibbles
+            .into_iter()
+            .zip(MOVE_DIRECTION)
+            .zip(MOVE_DELTA)
+            .filter_map(|((nibble, direction), (dx, dy))| {
+                let new = (x + dx, y + dy);
+                if nibble >= self.open_from && self.contains(new) {
+                    Some((new, direction))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    // longest path reaching the exit (at most `max_len` long), subtrees near the start are
+    // explored in parallel and the rest of each subtree depth first on its own thread
+    fn longest_path(&self, max_len: Option<usize>) -> Option<String> {
+        let longest = Mutex::new(None);
+        let mut context = md5::Context::new();
+        context.consume(&self.passcode);
+        self.explore_longest(context, self.start, String::new(), max_len, &longest);
+        longest.into_inner().expect("lock shouldn't be poisoned")
+    }
+
+    // `context` has already consumed the passcode and `path`, so every step hashes one more byte
+    fn explore_longest(
+        &self,
+        context: md5::Context,
+        position: Point,
+        path: String,
+        max_len: Option<usize>,
+        longest: &Mutex<Option<String>>,
+    ) {
+        let at_max_len = max_len.is_some_and(|max_len| path.len() >= max_len);
+        if path.len() >= PARALLEL_DEPTH || at_max_len || position == self.exit {
+            let found = self.longest_from(context, position, path, max_len);
+            let mut longest = longest.lock().expect("lock shouldn't be poisoned");
+            if found.as_ref().map(String::len) > longest.as_ref().map(String::len) {
+                *longest = found;
+            }
+            return;
+        }
+        let digest = context.clone().compute();
+        self.open_doors(&digest, position)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .for_each(|(next, direction)| {
+                let mut context = context.clone();
+                context.consume([direction as u8]);
+                let mut path = path.clone();
+                path.push(direction);
+                self.explore_longest(context, next, path, max_len, longest);
+            });
+    }
+
+    fn longest_from(
+        &self,
+        context: md5::Context,
+        position: Point,
+        path: String,
+        max_len: Option<usize>,
+    ) -> Option<String> {
+        let mut longest: Option<String> = None;
+        let mut stack = vec![(context, position, path)];
+        while let Some((context, position, path)) = stack.pop() {
+            if max_len.is_some_and(|max_len| path.len() > max_len) {
+                continue;
+            }
+            if position == self.exit {
+                if longest
+                    .as_ref()
+                    .is_none_or(|longest| longest.len() < path.len())
+                {
+                    longest = Some(path);
+                }
+                continue;
+            }
+            if max_len.is_some_and(|max_len| path.len() >= max_len) {
+                continue;
+            }
+            let digest = context.clone().compute();
+            for (next, direction) in self.open_doors(&digest, position) {
+                let mut context = context.clone();
+                context.consume([direction as u8]);
+                let mut path = path.clone();
+                path.push(direction);
+                stack.push((context, next, path));
+            }
+        }
+        longest
+    }
+
+    // every path reaching the exit (shortest first) with its length, paths stop at the exit;
+    // `max_len` cuts the search for vaults that have endless paths
+    fn paths(&self, max_len: Option<usize>) -> Vec<(usize, String)> {
+        let mut walker = AllPathsWalker {
+            vault: self,
+            max_len,
+            paths: Vec::new(),
+        };
+        walk::walk_broad(&mut walker, self.start_state());
+        walker.paths
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
 struct State {
     x: isize,
@@ -12,12 +219,6 @@ struct State {
     path: String,
 }
 
-impl State {
-    fn is_final(&self) -> bool {
-        self.x == 3 && self.y == 3
-    }
-}
-
 const MOVE_DIRECTION: [char; 4] = ['U', 'D', 'L', 'R'];
 const MOVE_DELTA: [(isize, isize); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
 
@@ -27,33 +228,13 @@ struct StateTransitionsIterator {
 }
 
 impl StateTransitionsIterator {
-    fn new(prefix: &str, state: &State) -> Self {
-        let digest: [u8; 16] = md5::compute(format!("{}{}", prefix, state.path)).into();
-        let nibbles = [
-            digest[0] >> 4,
-            digest[0] & 0x0f,
-            digest[1] >> 4,
-            digest[1] & 0x0f,
-        ];
+    fn new(vault: &Vault, state: &State) -> Self {
+        let digest = md5::compute(format!("{}{}", vault.passcode, state.path));
 
         // prefill
         Self {
             path: state.path.clone(),
-            iter: nibbles
-                .into_iter()
-                .zip(MOVE_DIRECTION.into_iter())
-                .zip(MOVE_DELTA.into_iter())
-                .filter_map(|((nibble, direction), (dx, dy))| {
-                    let new_x = state.x + dx;
-                    let new_y = state.y + dy;
-                    if nibble >= 0x0b && (0..4).contains(&new_x) && (0..4).contains(&new_y) {
-                        Some(((new_x, new_y), direction))
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>()
-                .into_iter(),
+            iter: vault.open_doors(&digest, (state.x, state.y)),
         }
     }
 }
@@ -71,54 +252,50 @@ impl Iterator for StateTransitionsIterator {
     }
 }
 
-struct FindFirstWalker {
-    prefix: String,
+struct FindFirstWalker<'a> {
+    vault: &'a Vault,
 }
 
-impl FindFirstWalker {
-    fn new(prefix: String) -> Self {
-        Self { prefix }
+impl<'a> FindFirstWalker<'a> {
+    fn new(vault: &'a Vault) -> Self {
+        Self { vault }
     }
 }
 
-impl walk::Walker<State> for FindFirstWalker {
+impl walk::Walker<State> for FindFirstWalker<'_> {
     type NextGenerator = StateTransitionsIterator;
     type Result = String;
 
     fn visit(&mut self, state: &State) -> walk::VisitDecision<Self::Result, Self::NextGenerator> {
-        if state.is_final() {
+        if self.vault.is_final(state) {
             return walk::VisitDecision::Break(state.path.clone());
         }
-        walk::VisitDecision::Next(StateTransitionsIterator::new(&self.prefix, state))
+        walk::VisitDecision::Next(StateTransitionsIterator::new(self.vault, state))
     }
 }
 
-struct FindLongestWalker {
-    prefix: String,
-    best_length: usize,
-}
-
-impl FindLongestWalker {
-    fn new(prefix: String) -> Self {
-        Self {
-            prefix,
-            best_length: 0,
-        }
-    }
+struct AllPathsWalker<'a> {
+    vault: &'a Vault,
+    max_len: Option<usize>,
+    paths: Vec<(usize, String)>,
 }
 
-impl walk::Walker<State> for FindLongestWalker {
+impl walk::Walker<State> for AllPathsWalker<'_> {
     type NextGenerator = StateTransitionsIterator;
     type Result = ();
 
     fn visit(&mut self, state: &State) -> walk::VisitDecision<Self::Result, Self::NextGenerator> {
-        if state.is_final() {
-            if self.best_length < state.path.len() {
-                self.best_length = state.path.len()
-            }
+        if self.vault.is_final(state) {
+            self.paths.push((state.path.len(), state.path.clone()));
+            return walk::VisitDecision::Continue;
+        }
+        if self
+            .max_len
+            .is_some_and(|max_len| state.path.len() >= max_len)
+        {
             return walk::VisitDecision::Continue;
         }
-        walk::VisitDecision::Next(StateTransitionsIterator::new(&self.prefix, state))
+        walk::VisitDecision::Next(StateTransitionsIterator::new(self.vault, state))
     }
 }
 
@@ -128,20 +305,90 @@ impl Problem for Day17 {
     type Part2 = usize;
 
     fn solve_part1(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part1 {
-        let mut walker = FindFirstWalker::new(input.to_owned());
-        walk::walk_broad(&mut walker, Default::default())
+        let vault = Vault::new(input);
+        let mut walker = FindFirstWalker::new(&vault);
+        walk::walk_broad(&mut walker, vault.start_state())
             .expect("walker should break and there should be a solution")
     }
 
     fn solve_part2(input: &<Self::Input as aoc_helpers::scaffold::Parse>::Parsed) -> Self::Part2 {
-        let mut walker = FindLongestWalker::new(input.to_owned());
-        walk::walk_deep(&mut walker, Default::default());
-        walker.best_length
+        Vault::new(input)
+            .longest_path(None)
+            .expect("there should be a path")
+            .len()
     }
 }
 
+fn parse_point(s: &str) -> anyhow::Result<Point> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| anyhow::anyhow!("Expected x,y: {}", s))?;
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<isize>()
+            .map_err(|e| anyhow::anyhow!("Int parse error: {}", e))
+    };
+    Ok((parse(x)?, parse(y)?))
+}
+
 fn main() {
-    solve::<Day17>(include_str!("../../inputs/day17.txt"));
+    let input = include_str!("../../inputs/day17.txt").trim();
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() {
+        return solve::<Day17>(input);
+    }
+    // day17 [passcode] [--size=WxH] [--start=x,y] [--exit=x,y] [--open=<hex digit>]
+    //       [--max-len=N] [--longest]
+    let option = |name: &str| {
+        args.iter()
+            .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))
+    };
+    let point = |name: &str, default: Point| {
+        option(name)
+            .map_or(Ok(default), parse_point)
+            .expect("point should parse")
+    };
+    let passcode = args
+        .iter()
+        .find(|arg| !arg.starts_with("--"))
+        .map_or(input, String::as_str);
+    let (width, height) = option("--size").map_or(VAULT_SIZE, |size| {
+        let (width, height) = size.split_once('x').expect("size should be WxH");
+        (
+            width.parse().expect("width should be a number"),
+            height.parse().expect("height should be a number"),
+        )
+    });
+    let vault = Vault {
+        width,
+        height,
+        start: point("--start", START),
+        exit: point("--exit", EXIT),
+        open_from: option("--open").map_or(OPEN_FROM, |open| {
+            u8::from_str_radix(open, 16).expect("open should be a hex digit")
+        }),
+        ..Vault::new(passcode)
+    };
+    let max_len = option("--max-len").map(|max_len| {
+        max_len
+            .parse::<usize>()
+            .expect("max length should be a number")
+    });
+    if let Err(e) = vault.validate(max_len) {
+        return eprintln!("{e}");
+    }
+    if args.iter().any(|arg| arg == "--longest") {
+        match vault.longest_path(max_len) {
+            Some(path) => println!("{} {}", path.len(), path),
+            None => println!("exit can't be reached"),
+        }
+        return;
+    }
+    let paths = vault.paths(max_len);
+    for (len, path) in &paths {
+        println!("{} {}", len, path);
+    }
+    println!("{} paths", paths.len());
 }
 
 #[cfg(test)]
@@ -165,4 +412,156 @@ mod tests {
         assert_eq!(solve_part2::<Day17>("kglvqrro"), 492);
         assert_eq!(solve_part2::<Day17>("ulqzkmiv"), 830);
     }
+
+    #[test]
+    fn test_paths() {
+        let paths = Vault::new("ihgpwlah").paths(None);
+        assert_eq!(paths.first(), Some(&(6, "DDRRRD".to_owned())));
+        assert_eq!(paths.last().map(|(len, _)| *len), Some(370));
+        assert!(paths.iter().all(|(len, path)| *len == path.len()));
+        assert!(paths.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert_eq!(
+            Vault::new("ihgpwlah").paths(Some(10)),
+            paths
+                .iter()
+                .filter(|(len, _)| *len <= 10)
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+        // no open doors from the start
+        assert!(Vault::new("hijkl").paths(None).is_empty());
+        assert_eq!(Vault::new("hijkl").longest_path(None), None);
+    }
+
+    #[test]
+    fn test_longest_path() {
+        for passcode in ["ihgpwlah", "kglvqrro", "ulqzkmiv"] {
+            let vault = Vault::new(passcode);
+            let longest = vault.longest_path(None).unwrap();
+            let paths = vault.paths(None);
+            assert_eq!(longest.len(), paths.last().unwrap().0);
+            assert!(paths.iter().any(|(_, path)| *path == longest));
+        }
+        let small = Vault {
+            width: 3,
+            height: 3,
+            exit: (2, 2),
+            ..Vault::new("ihgpwlah")
+        };
+        assert_eq!(
+            small.longest_path(None).map(|path| path.len()),
+            small.paths(None).last().map(|(len, _)| *len)
+        );
+        // shorter than the shortest path and the parallel depth
+        let vault = Vault::new("ihgpwlah");
+        const { assert!(5 < PARALLEL_DEPTH) };
+        assert_eq!(vault.paths(Some(5)), []);
+        assert_eq!(vault.longest_path(Some(5)), None);
+        assert_eq!(vault.longest_path(Some(6)).as_deref(), Some("DDRRRD"));
+        for max_len in 0..=PARALLEL_DEPTH + 12 {
+            assert_eq!(
+                vault.longest_path(Some(max_len)).map(|path| path.len()),
+                vault.paths(Some(max_len)).last().map(|(len, _)| *len),
+                "{}",
+                max_len
+            );
+        }
+        // below and above the parallel depth
+        for max_len in [6, 30] {
+            let bigger = Vault {
+                width: 5,
+                height: 5,
+                exit: (4, 4),
+                ..Vault::new("ihgpwlah")
+            };
+            assert_eq!(
+                bigger.longest_path(Some(max_len)).map(|path| path.len()),
+                bigger.paths(Some(max_len)).last().map(|(len, _)| *len)
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let vault = Vault::new("ihgpwlah");
+        assert!(vault.validate(None).is_ok());
+        for invalid in [
+            Vault {
+                width: 0,
+                ..vault.clone()
+            },
+            Vault {
+                start: (4, 0),
+                ..vault.clone()
+            },
+            Vault {
+                exit: (-1, 3),
+                ..vault.clone()
+            },
+            Vault {
+                open_from: 0xff,
+                ..vault.clone()
+            },
+        ] {
+            assert!(invalid.validate(Some(10)).is_err(), "{:?}", invalid);
+        }
+        for open_from in [0, 0x09, 0x0a] {
+            let open = Vault {
+                open_from,
+                ..vault.clone()
+            };
+            assert!(open.validate(None).is_err(), "{:x}", open_from);
+            assert!(open.validate(Some(10)).is_ok());
+        }
+        let bigger = Vault {
+            width: 5,
+            height: 5,
+            ..vault.clone()
+        };
+        assert!(bigger.validate(None).is_err());
+        assert!(bigger.validate(Some(30)).is_ok());
+        // fewer doors open or a smaller vault only cut paths short
+        let closed = Vault {
+            open_from: 0x0c,
+            width: 3,
+            exit: (2, 3),
+            ..vault.clone()
+        };
+        assert!(closed.validate(None).is_ok());
+    }
+
+    #[test]
+    fn test_custom_vault() {
+        let vault = Vault {
+            exit: (0, 0),
+            ..Vault::new("ihgpwlah")
+        };
+        assert_eq!(vault.paths(None), vec![(0, String::new())]);
+
+        // every door open in a corridor: only moves right and back left keep it inside
+        let corridor = Vault {
+            width: 3,
+            height: 1,
+            exit: (2, 0),
+            open_from: 0,
+            ..Vault::new("ihgpwlah")
+        };
+        assert_eq!(
+            corridor.paths(Some(4)),
+            vec![(2, "RR".to_owned()), (4, "RLRR".to_owned())]
+        );
+
+        let bigger = Vault {
+            width: 5,
+            height: 5,
+            exit: (4, 4),
+            ..Vault::new("ihgpwlah")
+        };
+        let paths = bigger.paths(Some(30));
+        assert_eq!(paths.first().map(|(len, _)| *len), Some(10));
+        let moves = |path: &str, dir| path.matches(dir).count() as isize;
+        assert!(paths.iter().all(|(_, path)| {
+            moves(path, 'D') - moves(path, 'U') == 4 && moves(path, 'R') - moves(path, 'L') == 4
+        }));
+    }
 }